@@ -0,0 +1,51 @@
+use std::fmt::Write;
+
+use crate::{DecodeError, Instruction};
+
+/// One instruction of a disassembled program, or the reason it couldn't be decoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisassembledLine {
+    /// Byte offset of the instruction in the program
+    pub offset: usize,
+    pub instruction: Result<Instruction, DecodeError>,
+}
+
+/// Walks the whole program and decodes every instruction.
+/// An illegal opcode is skipped byte per byte, a truncated instruction ends the program.
+pub fn disassemble(program: &[u8]) -> Vec<DisassembledLine> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < program.len() {
+        let instruction = Instruction::decode(program, offset);
+        let width = match &instruction {
+            Ok(instruction) => instruction.width(),
+            Err(DecodeError::IllegalOpcode { .. }) => 1,
+            Err(DecodeError::Truncated { .. } | DecodeError::EndOfProgram { .. }) => program.len() - offset,
+        };
+        lines.push(DisassembledLine { offset, instruction });
+        offset += width;
+    }
+    lines
+}
+
+/// Human readable listing of the program, one instruction per line prefixed by its byte offset
+pub fn listing(program: &[u8]) -> String {
+    let mut output = String::new();
+    for line in disassemble(program) {
+        let _ = match line.instruction {
+            Ok(instruction) => writeln!(output, "{:04}  {}", line.offset, instruction),
+            Err(DecodeError::IllegalOpcode { byte, .. }) => writeln!(output, "{:04}  ILG 0x{:02X}", line.offset, byte),
+            Err(error) => writeln!(output, "{:04}  ; {}", line.offset, error),
+        };
+    }
+    output
+}
+
+/// Reg-Byte assembly of the program, that the assembler turns back into the same bytes
+pub fn to_source(program: &[u8]) -> Result<String, DecodeError> {
+    let mut output = String::new();
+    for line in disassemble(program) {
+        let _ = writeln!(output, "{}", line.instruction?);
+    }
+    Ok(output)
+}
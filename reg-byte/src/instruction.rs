@@ -0,0 +1,157 @@
+use std::fmt;
+
+use crate::{OpCode, OperandKind};
+
+/// Decoded value of an operand
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    /// Register index `$N`
+    Register(u8),
+    /// Immediate number `#N`
+    Immediate(i32),
+    /// Float register index `$fN`
+    FloatRegister(u8),
+    /// Immediate float `#N.N`
+    Float(f64),
+}
+impl Operand {
+    /// Kind of operand this value is encoded as
+    pub fn kind(self) -> OperandKind {
+        match self {
+            Operand::Register(_) => OperandKind::Register,
+            Operand::Immediate(_) => OperandKind::Immediate32,
+            Operand::FloatRegister(_) => OperandKind::FloatRegister,
+            Operand::Float(_) => OperandKind::Float64,
+        }
+    }
+    /// Appends the encoded operand to the program
+    pub fn encode(self, program: &mut Vec<u8>) {
+        match self {
+            Operand::Register(register) => program.push(register),
+            Operand::Immediate(number) => program.extend_from_slice(&number.to_be_bytes()),
+            Operand::FloatRegister(register) => program.push(register),
+            Operand::Float(number) => program.extend_from_slice(&number.to_be_bytes()),
+        }
+    }
+    /// Decodes an operand of the given kind at the start of `bytes`, which must be long enough
+    fn decode(kind: OperandKind, bytes: &[u8]) -> Operand {
+        match kind {
+            OperandKind::Register => Operand::Register(bytes[0]),
+            OperandKind::Immediate32 => Operand::Immediate(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            OperandKind::FloatRegister => Operand::FloatRegister(bytes[0]),
+            OperandKind::Float64 => {
+                let mut number = [0; 8];
+                number.copy_from_slice(&bytes[..8]);
+                Operand::Float(f64::from_be_bytes(number))
+            },
+        }
+    }
+}
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Register(register) => write!(f, "${}", register),
+            Operand::Immediate(number) => write!(f, "#{}", number),
+            Operand::FloatRegister(register) => write!(f, "$f{}", register),
+            // Debug formatting always keeps a decimal point or an exponent, so the float can be assembled again
+            Operand::Float(number) => write!(f, "#{:?}", number),
+        }
+    }
+}
+
+/// Largest number of operands taken by an opcode
+pub const MAX_OPERANDS: usize = 3;
+
+/// An opcode with the values of its operands.
+/// The operands are kept inline so that decoding an instruction doesn't allocate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instruction {
+    pub opcode: OpCode,
+    operands: [Operand; MAX_OPERANDS],
+    operand_count: u8,
+}
+impl Instruction {
+    /// Panics if there are more than `MAX_OPERANDS` operands
+    pub fn new(opcode: OpCode, operands: &[Operand]) -> Instruction {
+        assert!(operands.len() <= MAX_OPERANDS, "{} operands, at most {} are allowed", operands.len(), MAX_OPERANDS);
+        let mut inline = [Operand::Register(0); MAX_OPERANDS];
+        inline[..operands.len()].copy_from_slice(operands);
+        Instruction {
+            opcode,
+            operands: inline,
+            operand_count: operands.len() as u8,
+        }
+    }
+    /// Values of the operands, in order
+    pub fn operands(&self) -> &[Operand] {
+        &self.operands[..self.operand_count as usize]
+    }
+    /// Decodes the instruction starting at `offset` in the program
+    pub fn decode(program: &[u8], offset: usize) -> Result<Instruction, DecodeError> {
+        let byte = *program.get(offset).ok_or(DecodeError::EndOfProgram { offset })?;
+        let opcode = OpCode::from(byte);
+        if opcode == OpCode::ILG {
+            return Err(DecodeError::IllegalOpcode { offset, byte });
+        }
+        let available = program.len() - offset - 1;
+        let needed = opcode.width() - 1;
+        if available < needed {
+            return Err(DecodeError::Truncated { offset, opcode, needed, available });
+        }
+        let mut instruction = Instruction::new(opcode, &[]);
+        let mut cursor = offset + 1;
+        for &kind in opcode.operands() {
+            instruction.operands[instruction.operand_count as usize] = Operand::decode(kind, &program[cursor..]);
+            instruction.operand_count += 1;
+            cursor += kind.width();
+        }
+        Ok(instruction)
+    }
+    /// Appends the encoded instruction to the program
+    pub fn encode(&self, program: &mut Vec<u8>) {
+        program.push(self.opcode as u8);
+        for operand in self.operands() {
+            operand.encode(program);
+        }
+    }
+    /// Number of bytes used by the encoded instruction
+    pub fn width(&self) -> usize {
+        self.opcode.width()
+    }
+}
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.opcode.mnemonic())?;
+        for operand in self.operands() {
+            write!(f, " {}", operand)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when bytes can't be decoded as an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The byte at `offset` isn't a known opcode
+    IllegalOpcode { offset: usize, byte: u8 },
+    /// The program ends before all the operands of the opcode at `offset`
+    Truncated { offset: usize, opcode: OpCode, needed: usize, available: usize },
+    /// `offset` is at or after the end of the program, there is no opcode to decode
+    EndOfProgram { offset: usize },
+}
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::IllegalOpcode { offset, byte } => {
+                write!(f, "illegal opcode 0x{:02X} at byte {}", byte, offset)
+            },
+            DecodeError::Truncated { offset, opcode, needed, available } => {
+                write!(f, "truncated {} at byte {} ({} operand bytes needed, {} left)", opcode.mnemonic(), offset, needed, available)
+            },
+            DecodeError::EndOfProgram { offset } => {
+                write!(f, "no instruction at byte {}, after the end of the program", offset)
+            },
+        }
+    }
+}
+impl std::error::Error for DecodeError {}
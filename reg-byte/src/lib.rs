@@ -1,9 +1,22 @@
+mod disassembler;
+mod instruction;
+pub mod object;
+pub mod rbg;
+
+pub use disassembler::{disassemble, listing, to_source, DisassembledLine};
+pub use instruction::{DecodeError, Instruction, Operand, MAX_OPERANDS};
+
+/// Number of registers of the VM, register operands go from `$0` to `$31` and hold 64 bits integers,
+/// and float register operands from `$f0` to `$f31`
+pub const REGISTER_COUNT: usize = 32;
+
 #[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// OpCode enum for the VM
 pub enum OpCode {
     /// Stop the program
     HLT,
-    /// Load a number into a register `LOAD REGISTER NUMBER`
+    /// Load a 32 bits number, sign extended to 64 bits, into a register `STORE REGISTER NUMBER`
     STORE,
     /// Add two registers and store the result in a third register `ADD REGISTER1 REGISTER2 REGISTER3`
     ADD,
@@ -11,7 +24,7 @@ pub enum OpCode {
     MUL,
     /// Subtract two registers and store the result in a third register `SUB REGISTER1 REGISTER2 REGISTER3`
     SUB,
-    /// Divide two registers and store the result in a third register `DIV REGISTER1 REGISTER2 REGISTER3` and store the remainder in the remainder register
+    /// Divide two signed registers and store the result in a third register `DIV REGISTER1 REGISTER2 REGISTER3` and store the remainder in the remainder register
     DIV,
     /// Jump to a byte in the program (based on a value stored in a register) `JMP REGISTER`
     JMP,
@@ -23,13 +36,13 @@ pub enum OpCode {
     EQ,
     /// Not equal to `NEQ REGISTER1 REGISTER2`
     NEQ,
-    /// Greater than `GT REGISTER1 REGISTER2`
+    /// Signed greater than `GT REGISTER1 REGISTER2`
     GT,
-    /// Less than `LT REGISTER1 REGISTER2`
+    /// Signed less than `LT REGISTER1 REGISTER2`
     LT,
-    /// Greater than or equal to `GTE REGISTER1 REGISTER2`
+    /// Signed greater than or equal to `GTE REGISTER1 REGISTER2`
     GTE,
-    /// Less than or equal to `LTE REGISTER1 REGISTER2`
+    /// Signed less than or equal to `LTE REGISTER1 REGISTER2`
     LTE,
     /// Jump to the byte stored in the register1 if the last comparison was true `JMPE REGISTER1`
     JMPE,
@@ -37,6 +50,110 @@ pub enum OpCode {
     PRINT,
     /// Print the value of a register with a newline `PRINTLN REGISTER`
     PRINTLN,
+    /// Push the address of the next instruction on the call stack and jump to the byte stored in the register `CALL REGISTER`
+    CALL,
+    /// Jump back to the address on top of the call stack `RET`
+    RET,
+    /// Push the value of a register on the stack `PUSH REGISTER`
+    PUSH,
+    /// Pop the value on top of the stack into a register `POP REGISTER`
+    POP,
+    /// Load the byte at the heap address stored in register1 into register2, zero extended `LDB REGISTER1 REGISTER2`
+    LDB,
+    /// Load the 16 bits at the heap address stored in register1 into register2, zero extended `LDH REGISTER1 REGISTER2`
+    LDH,
+    /// Load the 32 bits at the heap address stored in register1 into register2, sign extended `LDW REGISTER1 REGISTER2`
+    LDW,
+    /// Store the lowest byte of register1 at the heap address stored in register2 `STB REGISTER1 REGISTER2`
+    STB,
+    /// Store the lowest 16 bits of register1 at the heap address stored in register2 `STH REGISTER1 REGISTER2`
+    STH,
+    /// Store the lowest 32 bits of register1 at the heap address stored in register2 `STW REGISTER1 REGISTER2`
+    STW,
+    /// Grow the heap by the number of bytes stored in a register `ALOC REGISTER`
+    ALOC,
+    /// Read an integer from the input into a register, the equal flag is false at the end of the input `READ REGISTER`
+    READ,
+    /// Read a byte from the input into a register, the equal flag is false at the end of the input `READC REGISTER`
+    READC,
+    /// Load a 64 bits float into a float register `FSTORE FLOAT_REGISTER FLOAT`
+    FSTORE,
+    /// Add two float registers and store the result in a third float register `FADD FLOAT_REGISTER1 FLOAT_REGISTER2 FLOAT_REGISTER3`
+    FADD,
+    /// Subtract two float registers and store the result in a third float register `FSUB FLOAT_REGISTER1 FLOAT_REGISTER2 FLOAT_REGISTER3`
+    FSUB,
+    /// Multiply two float registers and store the result in a third float register `FMUL FLOAT_REGISTER1 FLOAT_REGISTER2 FLOAT_REGISTER3`
+    FMUL,
+    /// Divide two float registers and store the result in a third float register `FDIV FLOAT_REGISTER1 FLOAT_REGISTER2 FLOAT_REGISTER3`
+    FDIV,
+    /// Float equal to `FEQ FLOAT_REGISTER1 FLOAT_REGISTER2`
+    FEQ,
+    /// Float not equal to `FNEQ FLOAT_REGISTER1 FLOAT_REGISTER2`
+    FNEQ,
+    /// Float greater than `FGT FLOAT_REGISTER1 FLOAT_REGISTER2`
+    FGT,
+    /// Float less than `FLT FLOAT_REGISTER1 FLOAT_REGISTER2`
+    FLT,
+    /// Float greater than or equal to `FGTE FLOAT_REGISTER1 FLOAT_REGISTER2`
+    FGTE,
+    /// Float less than or equal to `FLTE FLOAT_REGISTER1 FLOAT_REGISTER2`
+    FLTE,
+    /// Convert the integer in a register to a float in a float register `ITOF REGISTER FLOAT_REGISTER`
+    ITOF,
+    /// Convert the float in a float register to an integer in a register, rounding toward zero `FTOI FLOAT_REGISTER REGISTER`
+    FTOI,
+    /// Print the value of a float register `FPRINT FLOAT_REGISTER`
+    FPRINT,
+    /// Print the value of a float register with a newline `FPRINTLN FLOAT_REGISTER`
+    FPRINTLN,
+    /// Divide two unsigned registers and store the result in a third register `DIVU REGISTER1 REGISTER2 REGISTER3` and store the remainder in the remainder register
+    DIVU,
+    /// Unsigned greater than `GTU REGISTER1 REGISTER2`
+    GTU,
+    /// Unsigned less than `LTU REGISTER1 REGISTER2`
+    LTU,
+    /// Unsigned greater than or equal to `GTEU REGISTER1 REGISTER2`
+    GTEU,
+    /// Unsigned less than or equal to `LTEU REGISTER1 REGISTER2`
+    LTEU,
+    /// Load the 64 bits at the heap address stored in register1 into register2 `LDD REGISTER1 REGISTER2`
+    LDD,
+    /// Store the 64 bits of register1 at the heap address stored in register2 `STD REGISTER1 REGISTER2`
+    STD,
+    /// Bitwise and of two registers stored in a third register `AND REGISTER1 REGISTER2 REGISTER3`
+    AND,
+    /// Bitwise or of two registers stored in a third register `OR REGISTER1 REGISTER2 REGISTER3`
+    OR,
+    /// Bitwise exclusive or of two registers stored in a third register `XOR REGISTER1 REGISTER2 REGISTER3`
+    XOR,
+    /// Bitwise not of register1 stored in register2 `NOT REGISTER1 REGISTER2`
+    NOT,
+    /// Shift register1 left by the number of bits in register2 and store the result in register3 `SHL REGISTER1 REGISTER2 REGISTER3`
+    SHL,
+    /// Logical shift of register1 right by the number of bits in register2, filling with zeros, and store the result in register3 `SHR REGISTER1 REGISTER2 REGISTER3`
+    SHR,
+    /// Arithmetic shift of register1 right by the number of bits in register2, filling with the sign bit, and store the result in register3 `SAR REGISTER1 REGISTER2 REGISTER3`
+    SAR,
+    /// Signed remainder of the division of two registers stored in a third register `MOD REGISTER1 REGISTER2 REGISTER3`
+    MOD,
+    /// Copy the remainder of the last `DIV` or `DIVU` into a register `GETREM REGISTER`
+    GETREM,
+    /// Copy the flags into a register, bit 0 is the equal flag, bit 1 the carry flag and bit 2 the overflow flag `GETFLAG REGISTER`
+    GETFLAG,
+    /// Jump to the byte stored in the register1 if the last comparison was false `JMPNE REGISTER1`
+    JMPNE,
+    /// Copy register1 into register2 `MOV REGISTER1 REGISTER2`
+    MOV,
+    /// Add 1 to a register `INC REGISTER`
+    INC,
+    /// Subtract 1 from a register `DEC REGISTER`
+    DEC,
+    /// Negate register1 and store the result in register2 `NEG REGISTER1 REGISTER2`
+    NEG,
+    /// Load the byte at the heap address stored in register1 into register2, sign extended `LDBS REGISTER1 REGISTER2`
+    LDBS,
+    /// Load the 16 bits at the heap address stored in register1 into register2, sign extended `LDHS REGISTER1 REGISTER2`
+    LDHS,
     /// Illegal opcode
     ILG,
 }
@@ -62,18 +179,196 @@ impl From<u8> for OpCode {
             15 => OpCode::JMPE,
             16 => OpCode::PRINT,
             17 => OpCode::PRINTLN,
+            18 => OpCode::CALL,
+            19 => OpCode::RET,
+            20 => OpCode::PUSH,
+            21 => OpCode::POP,
+            22 => OpCode::LDB,
+            23 => OpCode::LDH,
+            24 => OpCode::LDW,
+            25 => OpCode::STB,
+            26 => OpCode::STH,
+            27 => OpCode::STW,
+            28 => OpCode::ALOC,
+            29 => OpCode::READ,
+            30 => OpCode::READC,
+            31 => OpCode::FSTORE,
+            32 => OpCode::FADD,
+            33 => OpCode::FSUB,
+            34 => OpCode::FMUL,
+            35 => OpCode::FDIV,
+            36 => OpCode::FEQ,
+            37 => OpCode::FNEQ,
+            38 => OpCode::FGT,
+            39 => OpCode::FLT,
+            40 => OpCode::FGTE,
+            41 => OpCode::FLTE,
+            42 => OpCode::ITOF,
+            43 => OpCode::FTOI,
+            44 => OpCode::FPRINT,
+            45 => OpCode::FPRINTLN,
+            46 => OpCode::DIVU,
+            47 => OpCode::GTU,
+            48 => OpCode::LTU,
+            49 => OpCode::GTEU,
+            50 => OpCode::LTEU,
+            51 => OpCode::LDD,
+            52 => OpCode::STD,
+            53 => OpCode::AND,
+            54 => OpCode::OR,
+            55 => OpCode::XOR,
+            56 => OpCode::NOT,
+            57 => OpCode::SHL,
+            58 => OpCode::SHR,
+            59 => OpCode::SAR,
+            60 => OpCode::MOD,
+            61 => OpCode::GETREM,
+            62 => OpCode::GETFLAG,
+            63 => OpCode::JMPNE,
+            64 => OpCode::MOV,
+            65 => OpCode::INC,
+            66 => OpCode::DEC,
+            67 => OpCode::NEG,
+            68 => OpCode::LDBS,
+            69 => OpCode::LDHS,
             _ => OpCode::ILG,
         }
     }
 }
 
-/*pub struct Instruction {
-    opcode: OpCode,
+/// Kind of the operands following an opcode, the assembler and the VM both encode and decode through it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    /// Index of a register on one byte `$N`
+    Register,
+    /// Signed number on four bytes, big endian `#N`
+    Immediate32,
+    /// Index of a float register on one byte `$fN`
+    FloatRegister,
+    /// 64 bits float, big endian `#N.N`
+    Float64,
+}
+impl OperandKind {
+    /// Number of bytes used by the operand
+    pub fn width(self) -> usize {
+        match self {
+            OperandKind::Register => 1,
+            OperandKind::Immediate32 => 4,
+            OperandKind::FloatRegister => 1,
+            OperandKind::Float64 => 8,
+        }
+    }
 }
-impl Instruction {
-    pub fn new(opcode: OpCode) -> Instruction {
-      Instruction {
-        opcode
-      }
+
+impl OpCode {
+    /// Name of the opcode in Reg-Byte assembly
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            OpCode::HLT => "HLT",
+            OpCode::STORE => "STORE",
+            OpCode::ADD => "ADD",
+            OpCode::MUL => "MUL",
+            OpCode::SUB => "SUB",
+            OpCode::DIV => "DIV",
+            OpCode::JMP => "JMP",
+            OpCode::JMPF => "JMPF",
+            OpCode::JMPB => "JMPB",
+            OpCode::EQ => "EQ",
+            OpCode::NEQ => "NEQ",
+            OpCode::GT => "GT",
+            OpCode::LT => "LT",
+            OpCode::GTE => "GTE",
+            OpCode::LTE => "LTE",
+            OpCode::JMPE => "JMPE",
+            OpCode::PRINT => "PRINT",
+            OpCode::PRINTLN => "PRINTLN",
+            OpCode::CALL => "CALL",
+            OpCode::RET => "RET",
+            OpCode::PUSH => "PUSH",
+            OpCode::POP => "POP",
+            OpCode::LDB => "LDB",
+            OpCode::LDH => "LDH",
+            OpCode::LDW => "LDW",
+            OpCode::STB => "STB",
+            OpCode::STH => "STH",
+            OpCode::STW => "STW",
+            OpCode::ALOC => "ALOC",
+            OpCode::READ => "READ",
+            OpCode::READC => "READC",
+            OpCode::FSTORE => "FSTORE",
+            OpCode::FADD => "FADD",
+            OpCode::FSUB => "FSUB",
+            OpCode::FMUL => "FMUL",
+            OpCode::FDIV => "FDIV",
+            OpCode::FEQ => "FEQ",
+            OpCode::FNEQ => "FNEQ",
+            OpCode::FGT => "FGT",
+            OpCode::FLT => "FLT",
+            OpCode::FGTE => "FGTE",
+            OpCode::FLTE => "FLTE",
+            OpCode::ITOF => "ITOF",
+            OpCode::FTOI => "FTOI",
+            OpCode::FPRINT => "FPRINT",
+            OpCode::FPRINTLN => "FPRINTLN",
+            OpCode::DIVU => "DIVU",
+            OpCode::GTU => "GTU",
+            OpCode::LTU => "LTU",
+            OpCode::GTEU => "GTEU",
+            OpCode::LTEU => "LTEU",
+            OpCode::LDD => "LDD",
+            OpCode::STD => "STD",
+            OpCode::AND => "AND",
+            OpCode::OR => "OR",
+            OpCode::XOR => "XOR",
+            OpCode::NOT => "NOT",
+            OpCode::SHL => "SHL",
+            OpCode::SHR => "SHR",
+            OpCode::SAR => "SAR",
+            OpCode::MOD => "MOD",
+            OpCode::GETREM => "GETREM",
+            OpCode::GETFLAG => "GETFLAG",
+            OpCode::JMPNE => "JMPNE",
+            OpCode::MOV => "MOV",
+            OpCode::INC => "INC",
+            OpCode::DEC => "DEC",
+            OpCode::NEG => "NEG",
+            OpCode::LDBS => "LDBS",
+            OpCode::LDHS => "LDHS",
+            OpCode::ILG => "ILG",
+        }
+    }
+    /// Layout of the bytes following the opcode in the program
+    pub fn operands(self) -> &'static [OperandKind] {
+        use OperandKind::*;
+        match self {
+            OpCode::HLT | OpCode::RET | OpCode::ILG => &[],
+            OpCode::STORE => &[Register, Immediate32],
+            OpCode::ADD | OpCode::MUL | OpCode::SUB | OpCode::DIV | OpCode::DIVU | OpCode::MOD => &[Register, Register, Register],
+            OpCode::MOV | OpCode::NEG => &[Register, Register],
+            OpCode::INC | OpCode::DEC => &[Register],
+            OpCode::AND | OpCode::OR | OpCode::XOR => &[Register, Register, Register],
+            OpCode::NOT => &[Register, Register],
+            OpCode::SHL | OpCode::SHR | OpCode::SAR => &[Register, Register, Register],
+            OpCode::JMP | OpCode::JMPF | OpCode::JMPB | OpCode::JMPE | OpCode::JMPNE => &[Register],
+            OpCode::GETREM | OpCode::GETFLAG => &[Register],
+            OpCode::EQ | OpCode::NEQ | OpCode::GT | OpCode::LT | OpCode::GTE | OpCode::LTE => &[Register, Register],
+            OpCode::GTU | OpCode::LTU | OpCode::GTEU | OpCode::LTEU => &[Register, Register],
+            OpCode::PRINT | OpCode::PRINTLN => &[Register],
+            OpCode::CALL | OpCode::PUSH | OpCode::POP => &[Register],
+            OpCode::LDB | OpCode::LDH | OpCode::LDW | OpCode::LDD | OpCode::LDBS | OpCode::LDHS => &[Register, Register],
+            OpCode::STB | OpCode::STH | OpCode::STW | OpCode::STD => &[Register, Register],
+            OpCode::ALOC => &[Register],
+            OpCode::READ | OpCode::READC => &[Register],
+            OpCode::FSTORE => &[FloatRegister, Float64],
+            OpCode::FADD | OpCode::FSUB | OpCode::FMUL | OpCode::FDIV => &[FloatRegister, FloatRegister, FloatRegister],
+            OpCode::FEQ | OpCode::FNEQ | OpCode::FGT | OpCode::FLT | OpCode::FGTE | OpCode::FLTE => &[FloatRegister, FloatRegister],
+            OpCode::ITOF => &[Register, FloatRegister],
+            OpCode::FTOI => &[FloatRegister, Register],
+            OpCode::FPRINT | OpCode::FPRINTLN => &[FloatRegister],
+        }
+    }
+    /// Number of bytes used by the opcode and its operands
+    pub fn width(self) -> usize {
+        1 + self.operands().iter().map(|kind| kind.width()).sum::<usize>()
     }
-}*/
\ No newline at end of file
+}
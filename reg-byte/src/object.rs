@@ -0,0 +1,322 @@
+//! `.rbo` object files, modules assembled separately and linked into a single `.rbg` file.
+//!
+//! Layout, every number is big endian and every name is UTF-8 prefixed by its length on 2 bytes:
+//!
+//! | Size | Content                                                       |
+//! |------|---------------------------------------------------------------|
+//! | 4    | Magic bytes `RBO\0`                                           |
+//! | 2    | Format version                                                |
+//! | 4    | Length of the code section                                    |
+//! | n    | Code section                                                  |
+//! | 4    | Number of exported symbols                                    |
+//! | n    | Exported symbols: name, then byte offset in the code on 4     |
+//! | 4    | Number of imported symbols                                    |
+//! | n    | Names of the imported symbols                                 |
+//! | 4    | Number of relocations                                         |
+//! | n    | Relocations: kind on 1 byte, offset on 4, import index on 4   |
+//! |      | for the kind 1 only                                           |
+//! | 4    | Adler-32 checksum of all the previous bytes                   |
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::rbg::{adler32, ByteReader, RbgFile, Truncated};
+
+/// Magic bytes at the start of every `.rbo` file
+pub const OBJECT_MAGIC: [u8; 4] = *b"RBO\0";
+/// Version of the format written by `ObjectFile`, files of another version are rejected
+pub const OBJECT_VERSION: u16 = 1;
+
+/// A module assembled on its own, with the addresses left to the linker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectFile {
+    /// Bytecode of the module, as if it started at offset 0
+    pub code: Vec<u8>,
+    /// Labels other modules can use
+    pub exports: Vec<Symbol>,
+    /// Names of the symbols exported by other modules that the module uses
+    pub imports: Vec<String>,
+    /// Immediates holding an address, patched once the module is placed
+    pub relocations: Vec<Relocation>,
+}
+
+/// Label exported by a module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// Byte offset of the label in the code of the module
+    pub offset: u32,
+}
+
+/// 32 bits immediate of the code to which the linker adds an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relocation {
+    /// The immediate is an offset in the module, the linker adds the address of the module
+    Local { offset: u32 },
+    /// The immediate is an offset from an imported symbol, the linker adds the address of `imports[import]`
+    Import { offset: u32, import: u32 },
+}
+impl Relocation {
+    /// Byte offset of the immediate in the code of the module
+    pub fn offset(&self) -> u32 {
+        match self {
+            Relocation::Local { offset } | Relocation::Import { offset, .. } => *offset,
+        }
+    }
+}
+
+impl ObjectFile {
+    /// Encodes the file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(26 + self.code.len());
+        bytes.extend_from_slice(&OBJECT_MAGIC);
+        bytes.extend_from_slice(&OBJECT_VERSION.to_be_bytes());
+        bytes.extend_from_slice(&(self.code.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.code);
+        bytes.extend_from_slice(&(self.exports.len() as u32).to_be_bytes());
+        for symbol in &self.exports {
+            write_name(&mut bytes, &symbol.name);
+            bytes.extend_from_slice(&symbol.offset.to_be_bytes());
+        }
+        bytes.extend_from_slice(&(self.imports.len() as u32).to_be_bytes());
+        for import in &self.imports {
+            write_name(&mut bytes, import);
+        }
+        bytes.extend_from_slice(&(self.relocations.len() as u32).to_be_bytes());
+        for relocation in &self.relocations {
+            match relocation {
+                Relocation::Local { offset } => {
+                    bytes.push(0);
+                    bytes.extend_from_slice(&offset.to_be_bytes());
+                },
+                Relocation::Import { offset, import } => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(&offset.to_be_bytes());
+                    bytes.extend_from_slice(&import.to_be_bytes());
+                },
+            }
+        }
+        bytes.extend_from_slice(&adler32(&bytes).to_be_bytes());
+        bytes
+    }
+    /// Decodes a file, checking its magic bytes, version, checksum and that every symbol and relocation is inside the code
+    pub fn from_bytes(bytes: &[u8]) -> Result<ObjectFile, ObjectError> {
+        let mut reader = ByteReader { bytes, position: 0 };
+        if reader.take(OBJECT_MAGIC.len())? != OBJECT_MAGIC {
+            return Err(ObjectError::BadMagic);
+        }
+        let version = u16::from_be_bytes(reader.array()?);
+        if version != OBJECT_VERSION {
+            return Err(ObjectError::UnsupportedVersion(version));
+        }
+        let code_length = u32::from_be_bytes(reader.array()?) as usize;
+        let code = reader.take(code_length)?.to_vec();
+        let mut exports = Vec::new();
+        for _ in 0..u32::from_be_bytes(reader.array()?) {
+            let name = read_name(&mut reader)?;
+            let offset = u32::from_be_bytes(reader.array()?);
+            if offset as usize > code.len() {
+                return Err(ObjectError::SymbolOutOfBounds { symbol: name, offset });
+            }
+            exports.push(Symbol { name, offset });
+        }
+        let mut imports = Vec::new();
+        for _ in 0..u32::from_be_bytes(reader.array()?) {
+            imports.push(read_name(&mut reader)?);
+        }
+        let mut relocations = Vec::new();
+        for _ in 0..u32::from_be_bytes(reader.array()?) {
+            let relocation = match reader.array::<1>()?[0] {
+                0 => Relocation::Local {
+                    offset: u32::from_be_bytes(reader.array()?),
+                },
+                1 => Relocation::Import {
+                    offset: u32::from_be_bytes(reader.array()?),
+                    import: u32::from_be_bytes(reader.array()?),
+                },
+                kind => return Err(ObjectError::UnknownRelocation(kind)),
+            };
+            if relocation.offset() as usize + 4 > code.len() {
+                return Err(ObjectError::RelocationOutOfBounds(relocation.offset()));
+            }
+            if let Relocation::Import { import, .. } = relocation {
+                if import as usize >= imports.len() {
+                    return Err(ObjectError::UndefinedImport(import));
+                }
+            }
+            relocations.push(relocation);
+        }
+        let computed = adler32(&bytes[..reader.position]);
+        let stored = u32::from_be_bytes(reader.array()?);
+        if stored != computed {
+            return Err(ObjectError::ChecksumMismatch { stored, computed });
+        }
+        if reader.position != bytes.len() {
+            return Err(ObjectError::TrailingBytes(bytes.len() - reader.position));
+        }
+        Ok(ObjectFile { code, exports, imports, relocations })
+    }
+    /// Writes the encoded file
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+    /// Reads and decodes a whole file
+    pub fn read<R: Read>(reader: &mut R) -> Result<ObjectFile, ObjectError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        ObjectFile::from_bytes(&bytes)
+    }
+}
+
+/// Places the modules one after the other, the program starts at the first instruction of the first module.
+/// Every relocation gets the address of its module or of the symbol it imports
+pub fn link(modules: &[ObjectFile]) -> Result<RbgFile, LinkError> {
+    let mut bases = Vec::with_capacity(modules.len());
+    let mut symbols: HashMap<&str, usize> = HashMap::new();
+    let mut code = Vec::new();
+    for (module, object) in modules.iter().enumerate() {
+        let base = code.len();
+        for symbol in &object.exports {
+            if symbols.insert(&symbol.name, base + symbol.offset as usize).is_some() {
+                return Err(LinkError::DuplicateSymbol {
+                    symbol: symbol.name.clone(),
+                    module,
+                });
+            }
+        }
+        bases.push(base);
+        code.extend_from_slice(&object.code);
+    }
+    for (module, object) in modules.iter().enumerate() {
+        for relocation in &object.relocations {
+            let address = match relocation {
+                Relocation::Local { .. } => bases[module],
+                Relocation::Import { import, offset } => {
+                    let symbol = object.imports.get(*import as usize).ok_or(LinkError::InvalidRelocation {
+                        offset: *offset,
+                        module,
+                    })?;
+                    *symbols.get(symbol.as_str()).ok_or_else(|| LinkError::UndefinedSymbol {
+                        symbol: symbol.clone(),
+                        module,
+                    })?
+                },
+            };
+            let offset = relocation.offset();
+            let start = bases[module] + offset as usize;
+            let immediate = object.code.get(offset as usize..offset as usize + 4).ok_or(LinkError::InvalidRelocation { offset, module })?;
+            let value = i32::from_be_bytes(immediate.try_into().expect("the slice has 4 bytes")) as i64 + address as i64;
+            let value = i32::try_from(value).map_err(|_| LinkError::AddressOverflow { offset, module })?;
+            code[start..start + 4].copy_from_slice(&value.to_be_bytes());
+        }
+    }
+    Ok(RbgFile::new(code))
+}
+
+/// Error returned when a `.rbo` file can't be read
+#[derive(Debug)]
+pub enum ObjectError {
+    Io(io::Error),
+    /// The file doesn't start with `OBJECT_MAGIC`
+    BadMagic,
+    /// The file was written with another version of the format
+    UnsupportedVersion(u16),
+    /// The file ends before the end of a section
+    Truncated,
+    /// A symbol name isn't valid UTF-8
+    InvalidName,
+    /// An exported symbol is outside of the code section
+    SymbolOutOfBounds { symbol: String, offset: u32 },
+    /// The kind of a relocation isn't 0 or 1
+    UnknownRelocation(u8),
+    /// The immediate of a relocation is outside of the code section
+    RelocationOutOfBounds(u32),
+    /// A relocation refers to an import that isn't in the table of imports
+    UndefinedImport(u32),
+    /// The content doesn't match the checksum, the file is corrupted
+    ChecksumMismatch { stored: u32, computed: u32 },
+    /// Bytes were found after the checksum
+    TrailingBytes(usize),
+}
+impl From<io::Error> for ObjectError {
+    fn from(error: io::Error) -> Self {
+        ObjectError::Io(error)
+    }
+}
+impl From<Truncated> for ObjectError {
+    fn from(_: Truncated) -> Self {
+        ObjectError::Truncated
+    }
+}
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjectError::Io(error) => write!(f, "{}", error),
+            ObjectError::BadMagic => write!(f, "not a .rbo file"),
+            ObjectError::UnsupportedVersion(version) => {
+                write!(f, "unsupported .rbo version {} (expected {})", version, OBJECT_VERSION)
+            },
+            ObjectError::Truncated => write!(f, "truncated .rbo file"),
+            ObjectError::InvalidName => write!(f, "symbol name isn't valid UTF-8"),
+            ObjectError::SymbolOutOfBounds { symbol, offset } => {
+                write!(f, "symbol {} at {} is outside of the code section", symbol, offset)
+            },
+            ObjectError::UnknownRelocation(kind) => write!(f, "unknown relocation kind {}", kind),
+            ObjectError::RelocationOutOfBounds(offset) => {
+                write!(f, "relocation at {} is outside of the code section", offset)
+            },
+            ObjectError::UndefinedImport(import) => write!(f, "relocation refers to the undefined import {}", import),
+            ObjectError::ChecksumMismatch { stored, computed } => {
+                write!(f, "corrupted .rbo file (checksum 0x{:08X}, expected 0x{:08X})", computed, stored)
+            },
+            ObjectError::TrailingBytes(count) => write!(f, "{} unexpected bytes after the end of the .rbo file", count),
+        }
+    }
+}
+impl std::error::Error for ObjectError {}
+
+/// Error returned by `link`, `module` is the index of the module in error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// Two modules export the same symbol
+    DuplicateSymbol { symbol: String, module: usize },
+    /// A module imports a symbol no module exports
+    UndefinedSymbol { symbol: String, module: usize },
+    /// A relocation is outside of the code or refers to an import that doesn't exist
+    InvalidRelocation { offset: u32, module: usize },
+    /// A relocated immediate doesn't fit in 32 bits
+    AddressOverflow { offset: u32, module: usize },
+}
+impl LinkError {
+    /// Returns the index of the module in error
+    pub fn module(&self) -> usize {
+        match self {
+            LinkError::DuplicateSymbol { module, .. }
+            | LinkError::UndefinedSymbol { module, .. }
+            | LinkError::InvalidRelocation { module, .. }
+            | LinkError::AddressOverflow { module, .. } => *module,
+        }
+    }
+}
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinkError::DuplicateSymbol { symbol, .. } => write!(f, "symbol {} is already exported by another module", symbol),
+            LinkError::UndefinedSymbol { symbol, .. } => write!(f, "undefined symbol {}", symbol),
+            LinkError::InvalidRelocation { offset, .. } => write!(f, "invalid relocation at {}", offset),
+            LinkError::AddressOverflow { offset, .. } => write!(f, "relocated immediate at {} doesn't fit in 32 bits", offset),
+        }
+    }
+}
+impl std::error::Error for LinkError {}
+
+fn write_name(bytes: &mut Vec<u8>, name: &str) {
+    bytes.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(name.as_bytes());
+}
+
+fn read_name(reader: &mut ByteReader) -> Result<String, ObjectError> {
+    let length = u16::from_be_bytes(reader.array()?) as usize;
+    String::from_utf8(reader.take(length)?.to_vec()).map_err(|_| ObjectError::InvalidName)
+}
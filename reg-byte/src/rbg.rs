@@ -0,0 +1,176 @@
+//! `.rbg` files, the portable container of a Reg-Byte program.
+//!
+//! Layout, every number is big endian:
+//!
+//! | Size | Content                                           |
+//! |------|---------------------------------------------------|
+//! | 4    | Magic bytes `RBG\0`                               |
+//! | 2    | Format version                                    |
+//! | 4    | Entry point, byte offset in the code section      |
+//! | 4    | Length of the code section                        |
+//! | n    | Code section                                      |
+//! | 4    | Length of the data section (0 if there is none)   |
+//! | n    | Data section                                      |
+//! | 4    | Adler-32 checksum of all the previous bytes       |
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// Magic bytes at the start of every `.rbg` file
+pub const RBG_MAGIC: [u8; 4] = *b"RBG\0";
+/// Version of the format written by `RbgFile`, files of another version are rejected
+pub const RBG_VERSION: u16 = 1;
+
+/// A program as stored in a `.rbg` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RbgFile {
+    /// Byte offset of the first instruction to execute
+    pub entry_point: u32,
+    /// Bytecode of the program
+    pub code: Vec<u8>,
+    /// Constants and initial data of the program
+    pub data: Vec<u8>,
+}
+impl RbgFile {
+    /// Wraps a program starting at its first byte, without data
+    pub fn new(code: Vec<u8>) -> RbgFile {
+        RbgFile {
+            entry_point: 0,
+            code,
+            data: vec![],
+        }
+    }
+    /// Encodes the file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(22 + self.code.len() + self.data.len());
+        bytes.extend_from_slice(&RBG_MAGIC);
+        bytes.extend_from_slice(&RBG_VERSION.to_be_bytes());
+        bytes.extend_from_slice(&self.entry_point.to_be_bytes());
+        bytes.extend_from_slice(&(self.code.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.code);
+        bytes.extend_from_slice(&(self.data.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes.extend_from_slice(&adler32(&bytes).to_be_bytes());
+        bytes
+    }
+    /// Decodes a file, checking its magic bytes, version, checksum and entry point
+    pub fn from_bytes(bytes: &[u8]) -> Result<RbgFile, RbgError> {
+        let mut reader = ByteReader { bytes, position: 0 };
+        if reader.take(RBG_MAGIC.len())? != RBG_MAGIC {
+            return Err(RbgError::BadMagic);
+        }
+        let version = u16::from_be_bytes(reader.array()?);
+        if version != RBG_VERSION {
+            return Err(RbgError::UnsupportedVersion(version));
+        }
+        let entry_point = u32::from_be_bytes(reader.array()?);
+        let code_length = u32::from_be_bytes(reader.array()?) as usize;
+        let code = reader.take(code_length)?.to_vec();
+        let data_length = u32::from_be_bytes(reader.array()?) as usize;
+        let data = reader.take(data_length)?.to_vec();
+        let computed = adler32(&bytes[..reader.position]);
+        let stored = u32::from_be_bytes(reader.array()?);
+        if stored != computed {
+            return Err(RbgError::ChecksumMismatch { stored, computed });
+        }
+        if reader.position != bytes.len() {
+            return Err(RbgError::TrailingBytes(bytes.len() - reader.position));
+        }
+        if entry_point as usize > code.len() {
+            return Err(RbgError::EntryPointOutOfBounds(entry_point));
+        }
+        Ok(RbgFile { entry_point, code, data })
+    }
+    /// Writes the encoded file
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+    /// Reads and decodes a whole file
+    pub fn read<R: Read>(reader: &mut R) -> Result<RbgFile, RbgError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        RbgFile::from_bytes(&bytes)
+    }
+}
+
+/// Error returned when a `.rbg` file can't be read
+#[derive(Debug)]
+pub enum RbgError {
+    Io(io::Error),
+    /// The file doesn't start with `RBG_MAGIC`
+    BadMagic,
+    /// The file was written with another version of the format
+    UnsupportedVersion(u16),
+    /// The file ends before the end of a section
+    Truncated,
+    /// The content doesn't match the checksum, the file is corrupted
+    ChecksumMismatch { stored: u32, computed: u32 },
+    /// Bytes were found after the checksum
+    TrailingBytes(usize),
+    /// The entry point is outside of the code section
+    EntryPointOutOfBounds(u32),
+}
+impl From<io::Error> for RbgError {
+    fn from(error: io::Error) -> Self {
+        RbgError::Io(error)
+    }
+}
+impl fmt::Display for RbgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RbgError::Io(error) => write!(f, "{}", error),
+            RbgError::BadMagic => write!(f, "not a .rbg file"),
+            RbgError::UnsupportedVersion(version) => {
+                write!(f, "unsupported .rbg version {} (expected {})", version, RBG_VERSION)
+            },
+            RbgError::Truncated => write!(f, "truncated .rbg file"),
+            RbgError::ChecksumMismatch { stored, computed } => {
+                write!(f, "corrupted .rbg file (checksum 0x{:08X}, expected 0x{:08X})", computed, stored)
+            },
+            RbgError::TrailingBytes(count) => write!(f, "{} unexpected bytes after the end of the .rbg file", count),
+            RbgError::EntryPointOutOfBounds(entry_point) => {
+                write!(f, "entry point {} is outside of the code section", entry_point)
+            },
+        }
+    }
+}
+impl std::error::Error for RbgError {}
+
+/// The file ends before a value being decoded
+pub(crate) struct Truncated;
+impl From<Truncated> for RbgError {
+    fn from(_: Truncated) -> Self {
+        RbgError::Truncated
+    }
+}
+
+/// Cursor over the bytes of a file being decoded
+pub(crate) struct ByteReader<'a> {
+    pub(crate) bytes: &'a [u8],
+    pub(crate) position: usize,
+}
+impl<'a> ByteReader<'a> {
+    pub(crate) fn take(&mut self, count: usize) -> Result<&'a [u8], Truncated> {
+        let end = self.position.checked_add(count).filter(|end| *end <= self.bytes.len()).ok_or(Truncated)?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], Truncated> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+}
+
+/// Adler-32 checksum, as defined by zlib
+pub(crate) fn adler32(bytes: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let mut a = 1u32;
+    let mut b = 0u32;
+    for byte in bytes {
+        a = (a + *byte as u32) % MOD;
+        b = (b + a) % MOD;
+    }
+    (b << 16) | a
+}
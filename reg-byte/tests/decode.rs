@@ -0,0 +1,31 @@
+use reg_byte::{DecodeError, Instruction, OpCode, Operand, MAX_OPERANDS};
+
+#[test]
+fn decode_past_the_end_is_an_error() {
+    assert_eq!(Instruction::decode(&[], 0), Err(DecodeError::EndOfProgram { offset: 0 }));
+    assert_eq!(Instruction::decode(&[OpCode::HLT as u8], 1), Err(DecodeError::EndOfProgram { offset: 1 }));
+}
+
+#[test]
+fn decode_reports_truncated_operands() {
+    let program = [OpCode::STORE as u8, 0, 0, 0];
+    assert_eq!(
+        Instruction::decode(&program, 0),
+        Err(DecodeError::Truncated { offset: 0, opcode: OpCode::STORE, needed: 5, available: 3 })
+    );
+}
+
+#[test]
+fn every_opcode_fits_the_inline_operands() {
+    for opcode in (0..=u8::MAX).map(OpCode::from) {
+        assert!(opcode.operands().len() <= MAX_OPERANDS, "{} has too many operands", opcode.mnemonic());
+    }
+}
+
+#[test]
+fn decoded_operands_follow_the_layout() {
+    let program = [OpCode::STORE as u8, 3, 0xFF, 0xFF, 0xFF, 0xFE, OpCode::HLT as u8];
+    assert_eq!(Instruction::decode(&program, 0).unwrap().operands(), [Operand::Register(3), Operand::Immediate(-2)]);
+    assert_eq!(Instruction::decode(&program, 6).unwrap().operands(), []);
+    assert_eq!(Instruction::decode(&program, 0), Ok(Instruction::new(OpCode::STORE, &[Operand::Register(3), Operand::Immediate(-2)])));
+}
@@ -0,0 +1,26 @@
+use reg_byte::object::{ObjectError, ObjectFile, Relocation, Symbol};
+
+fn file() -> ObjectFile {
+    ObjectFile {
+        code: vec![1, 0, 0, 0, 0, 7, 1, 1, 0, 0, 0, 2],
+        exports: vec![Symbol { name: "start".to_string(), offset: 0 }],
+        imports: vec!["table".to_string()],
+        relocations: vec![Relocation::Local { offset: 2 }, Relocation::Import { offset: 8, import: 0 }],
+    }
+}
+
+#[test]
+fn round_trip() {
+    let file = file();
+    assert_eq!(ObjectFile::from_bytes(&file.to_bytes()).unwrap(), file);
+    let mut written = Vec::new();
+    file.write(&mut written).unwrap();
+    assert_eq!(ObjectFile::read(&mut written.as_slice()).unwrap(), file);
+}
+
+#[test]
+fn checksum_mismatch() {
+    let mut bytes = file().to_bytes();
+    bytes[11] ^= 0xFF;
+    assert!(matches!(ObjectFile::from_bytes(&bytes), Err(ObjectError::ChecksumMismatch { stored, computed }) if stored != computed));
+}
@@ -0,0 +1,72 @@
+use reg_byte::rbg::{RbgError, RbgFile, RBG_VERSION};
+
+fn file() -> RbgFile {
+    RbgFile {
+        entry_point: 2,
+        code: vec![1, 0, 0, 0, 0, 7, 0],
+        data: vec![9, 8, 7],
+    }
+}
+
+/// Recomputes the Adler-32 checksum at the end of the bytes after they were modified
+fn reseal(bytes: &mut [u8]) {
+    let (content, checksum) = bytes.split_at_mut(bytes.len() - 4);
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in content.iter() {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    checksum.copy_from_slice(&((b << 16) | a).to_be_bytes());
+}
+
+#[test]
+fn round_trip() {
+    let file = file();
+    assert_eq!(RbgFile::from_bytes(&file.to_bytes()).unwrap(), file);
+    let mut written = Vec::new();
+    file.write(&mut written).unwrap();
+    assert_eq!(RbgFile::read(&mut written.as_slice()).unwrap(), file);
+}
+
+#[test]
+fn rejects_bad_magic() {
+    let mut bytes = file().to_bytes();
+    bytes[0] = b'X';
+    assert!(matches!(RbgFile::from_bytes(&bytes), Err(RbgError::BadMagic)));
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut bytes = file().to_bytes();
+    bytes[4..6].copy_from_slice(&(RBG_VERSION + 1).to_be_bytes());
+    reseal(&mut bytes);
+    assert!(matches!(RbgFile::from_bytes(&bytes), Err(RbgError::UnsupportedVersion(version)) if version == RBG_VERSION + 1));
+}
+
+#[test]
+fn rejects_corrupted_content() {
+    let mut bytes = file().to_bytes();
+    bytes[15] ^= 0xFF;
+    assert!(matches!(RbgFile::from_bytes(&bytes), Err(RbgError::ChecksumMismatch { .. })));
+}
+
+#[test]
+fn rejects_truncated_files() {
+    let bytes = file().to_bytes();
+    for length in 0..bytes.len() - 4 {
+        assert!(matches!(RbgFile::from_bytes(&bytes[..length]), Err(RbgError::Truncated)), "length {}", length);
+    }
+}
+
+#[test]
+fn rejects_trailing_bytes() {
+    let mut bytes = file().to_bytes();
+    bytes.extend_from_slice(&[0, 0]);
+    assert!(matches!(RbgFile::from_bytes(&bytes), Err(RbgError::TrailingBytes(2))));
+}
+
+#[test]
+fn rejects_entry_point_out_of_the_code() {
+    let file = RbgFile { entry_point: 8, ..file() };
+    assert!(matches!(RbgFile::from_bytes(&file.to_bytes()), Err(RbgError::EntryPointOutOfBounds(8))));
+}
@@ -0,0 +1,276 @@
+use std::fmt;
+
+use pest::error::ErrorVariant;
+use pest::iterators::Pair;
+
+use reg_byte::OperandKind;
+
+use crate::Rule;
+
+/// Position of a token in the source, both starting at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+impl Span {
+    /// Returns the position where the pair starts
+    pub(crate) fn of(pair: &Pair<Rule>) -> Self {
+        let (line, column) = pair.as_span().start_pos().line_col();
+        Self { line, column }
+    }
+}
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Error returned by `RegCompiler::compile` when the source can't be assembled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    /// The source doesn't match the grammar
+    Parse { message: String, span: Span },
+    /// A register operand isn't one of the VM registers
+    RegisterOutOfRange { register: String, span: Span },
+    /// An immediate is too big to be encoded
+    ImmediateOverflow { value: String, span: Span },
+    /// A float immediate is too big to be encoded
+    FloatOverflow { value: String, span: Span },
+    /// The instruction name isn't part of Reg-Byte
+    UnknownMnemonic { mnemonic: String, span: Span },
+    /// An immediate uses a name that is neither a label nor a constant
+    UndefinedLabel { label: String, span: Span },
+    /// A label is defined more than once, or has the name of a constant
+    DuplicateLabel { label: String, span: Span },
+    /// A `.equ` directive uses a name that isn't a constant defined before it
+    UndefinedConstant { constant: String, span: Span },
+    /// A constant is defined more than once, or has the name of a label
+    DuplicateConstant { constant: String, span: Span },
+    /// A register operand uses an alias that isn't defined
+    UndefinedAlias { alias: String, span: Span },
+    /// An alias is defined more than once
+    DuplicateAlias { alias: String, span: Span },
+    /// An alias is named like a float register, `$f1` would be ambiguous
+    AliasShadowsRegister { alias: String, span: Span },
+    /// An integer register is given where a float register is expected, or the opposite
+    WrongRegisterKind { register: String, expected: OperandKind, span: Span },
+    /// A constant expression doesn't fit in 64 bits
+    ExpressionOverflow { expression: String, span: Span },
+    /// A constant expression divides by zero
+    DivisionByZero { expression: String, span: Span },
+    /// A macro body uses a parameter the macro doesn't declare, or a parameter is used outside of a macro
+    UndefinedParameter { parameter: String, span: Span },
+    /// A macro declares the same parameter twice
+    DuplicateParameter { parameter: String, span: Span },
+    /// A macro is defined more than once
+    DuplicateMacro { name: String, span: Span },
+    /// A macro is named like an instruction, it could never be invoked
+    MacroShadowsInstruction { name: String, span: Span },
+    /// A macro is invoked with the wrong number of arguments
+    MacroArguments { name: String, expected: usize, found: usize, span: Span },
+    /// A macro invokes itself, its expansion would never end
+    MacroRecursion { name: String, span: Span },
+    /// The argument of a parameter isn't the kind of operand expected where the parameter is used
+    WrongArgument { parameter: String, argument: String, expected: OperandKind, span: Span },
+    /// An immediate of an object file uses addresses in a way the linker can't relocate
+    NotRelocatable { expression: String, span: Span },
+    /// The file of an `.include` directive can't be read
+    IncludeFailed { path: String, message: String, span: Span },
+    /// A file includes itself, directly or through other files
+    IncludeCycle { path: String, span: Span },
+    /// Error in an included file, `error` points in that file and `include` at the `.include` directive
+    InInclude { path: String, include: Span, error: Box<CompileError> },
+    /// Error in the body of a macro, `error` points at the macro definition and `invocation` at where it was expanded
+    InMacro { name: String, invocation: Span, error: Box<CompileError> },
+}
+impl CompileError {
+    /// Returns where the error happened in the source
+    pub fn span(&self) -> Span {
+        match self {
+            CompileError::Parse { span, .. }
+            | CompileError::RegisterOutOfRange { span, .. }
+            | CompileError::ImmediateOverflow { span, .. }
+            | CompileError::FloatOverflow { span, .. }
+            | CompileError::UnknownMnemonic { span, .. }
+            | CompileError::UndefinedLabel { span, .. }
+            | CompileError::DuplicateLabel { span, .. }
+            | CompileError::UndefinedConstant { span, .. }
+            | CompileError::DuplicateConstant { span, .. }
+            | CompileError::UndefinedAlias { span, .. }
+            | CompileError::DuplicateAlias { span, .. }
+            | CompileError::AliasShadowsRegister { span, .. }
+            | CompileError::WrongRegisterKind { span, .. }
+            | CompileError::ExpressionOverflow { span, .. }
+            | CompileError::DivisionByZero { span, .. }
+            | CompileError::UndefinedParameter { span, .. }
+            | CompileError::DuplicateParameter { span, .. }
+            | CompileError::DuplicateMacro { span, .. }
+            | CompileError::MacroShadowsInstruction { span, .. }
+            | CompileError::MacroArguments { span, .. }
+            | CompileError::MacroRecursion { span, .. }
+            | CompileError::WrongArgument { span, .. }
+            | CompileError::NotRelocatable { span, .. }
+            | CompileError::IncludeFailed { span, .. }
+            | CompileError::IncludeCycle { span, .. } => *span,
+            CompileError::InInclude { include, .. } => *include,
+            CompileError::InMacro { invocation, .. } => *invocation,
+        }
+    }
+}
+impl From<pest::error::Error<Rule>> for CompileError {
+    fn from(error: pest::error::Error<Rule>) -> Self {
+        let message = match &error.variant {
+            ErrorVariant::ParsingError { positives, .. } if !positives.is_empty() => format!("expected {}", describe_rules(positives)),
+            ErrorVariant::ParsingError { negatives, .. } if !negatives.is_empty() => format!("unexpected {}", describe_rules(negatives)),
+            ErrorVariant::ParsingError { .. } => "unexpected input".to_string(),
+            ErrorVariant::CustomError { message } => message.clone(),
+        };
+        let (line, column) = match error.line_col {
+            pest::error::LineColLocation::Pos(pos) => pos,
+            pest::error::LineColLocation::Span(start, _) => start,
+        };
+        CompileError::Parse {
+            message,
+            span: Span { line, column },
+        }
+    }
+}
+
+/// Lists what the rules stand for in the source, like pest does with the rule names: `a, b, or c`
+fn describe_rules(rules: &[Rule]) -> String {
+    let mut names: Vec<&str> = Vec::new();
+    for name in rules.iter().map(|rule| describe_rule(*rule)) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    match names.as_slice() {
+        [name] => name.to_string(),
+        [first, second] => format!("{} or {}", first, second),
+        [names @ .., last] => format!("{}, or {}", names.join(", "), last),
+        [] => String::new(),
+    }
+}
+
+/// What a rule of the grammar stands for in an error message
+fn describe_rule(rule: Rule) -> &'static str {
+    match rule {
+        // Only the end of a line can be followed by the end of the input, and pest doesn't report expected newlines
+        Rule::EOI => "end of line",
+        Rule::program => "a label, a directive, or an instruction",
+        Rule::REGISTER => "a register",
+        Rule::FLOAT_REGISTER => "a float register",
+        Rule::ALIAS_REF => "an alias",
+        Rule::MACRO_PARAM => "a macro parameter",
+        Rule::IMMEDIATE | Rule::EXPRESSION | Rule::PRODUCT | Rule::NEGATION | Rule::INTEGER => "an immediate",
+        Rule::PLUS | Rule::MINUS | Rule::TIMES | Rule::DIVIDE | Rule::REMAINDER => "an operator",
+        Rule::FLOAT => "a float",
+        Rule::STRING | Rule::STRING_CONTENT => "a quoted path",
+        Rule::IDENT | Rule::MACRO_PARAMETERS => "a name",
+        Rule::LABEL => "a label",
+        Rule::EQU | Rule::INCLUDE | Rule::GLOBAL | Rule::EXTERN | Rule::ALIAS | Rule::MACRO => "a directive",
+        Rule::MACRO_BODY => "the end of the macro",
+        // Every other rule is a mnemonic, an instruction or a macro invocation
+        _ => "an instruction",
+    }
+}
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::Parse { message, span } => {
+                write!(f, "{}: {}", span, message)
+            },
+            CompileError::RegisterOutOfRange { register, span } => {
+                let prefix = if register.starts_with("$f") { "$f" } else { "$" };
+                write!(f, "{}: register {} out of range (expected {}0 to {}{})", span, register, prefix, prefix, reg_byte::REGISTER_COUNT - 1)
+            },
+            CompileError::ImmediateOverflow { value, span } => {
+                write!(f, "{}: immediate {} doesn't fit in {} bits", span, value, i32::BITS)
+            },
+            CompileError::FloatOverflow { value, span } => {
+                write!(f, "{}: float {} doesn't fit in 64 bits", span, value)
+            },
+            CompileError::UnknownMnemonic { mnemonic, span } => {
+                write!(f, "{}: unknown mnemonic {}", span, mnemonic)
+            },
+            CompileError::UndefinedLabel { label, span } => {
+                write!(f, "{}: undefined label or constant {}", span, label)
+            },
+            CompileError::DuplicateLabel { label, span } => {
+                write!(f, "{}: label {} is already defined", span, label)
+            },
+            CompileError::UndefinedConstant { constant, span } => {
+                write!(f, "{}: undefined constant {}", span, constant)
+            },
+            CompileError::DuplicateConstant { constant, span } => {
+                write!(f, "{}: constant {} is already defined", span, constant)
+            },
+            CompileError::UndefinedAlias { alias, span } => {
+                write!(f, "{}: undefined register alias {}", span, alias)
+            },
+            CompileError::DuplicateAlias { alias, span } => {
+                write!(f, "{}: alias {} is already defined", span, alias)
+            },
+            CompileError::AliasShadowsRegister { alias, span } => {
+                write!(f, "{}: alias {} shadows the float register ${}", span, alias, alias)
+            },
+            CompileError::WrongRegisterKind { register, expected, span } => {
+                write!(f, "{}: expected {}, found {}", span, describe(*expected), register)
+            },
+            CompileError::ExpressionOverflow { expression, span } => {
+                write!(f, "{}: expression {} doesn't fit in 64 bits", span, expression)
+            },
+            CompileError::DivisionByZero { expression, span } => {
+                write!(f, "{}: expression {} divides by zero", span, expression)
+            },
+            CompileError::UndefinedParameter { parameter, span } => {
+                write!(f, "{}: undefined macro parameter {}", span, parameter)
+            },
+            CompileError::DuplicateParameter { parameter, span } => {
+                write!(f, "{}: parameter {} is already declared", span, parameter)
+            },
+            CompileError::DuplicateMacro { name, span } => {
+                write!(f, "{}: macro {} is already defined", span, name)
+            },
+            CompileError::MacroShadowsInstruction { name, span } => {
+                write!(f, "{}: macro {} has the name of an instruction", span, name)
+            },
+            CompileError::MacroArguments { name, expected, found, span } => {
+                write!(f, "{}: macro {} expects {} arguments, found {}", span, name, expected, found)
+            },
+            CompileError::MacroRecursion { name, span } => {
+                write!(f, "{}: macro {} invokes itself", span, name)
+            },
+            CompileError::WrongArgument { parameter, argument, expected, span } => {
+                write!(f, "{}: expected {}, found {} given to {}", span, describe(*expected), argument, parameter)
+            },
+            CompileError::NotRelocatable { expression, span } => {
+                write!(f, "{}: expression {} can't be relocated, it can only add a number to one label", span, expression)
+            },
+            CompileError::IncludeFailed { path, message, span } => {
+                write!(f, "{}: can't include {}: {}", span, path, message)
+            },
+            CompileError::IncludeCycle { path, span } => {
+                write!(f, "{}: {} includes itself", span, path)
+            },
+            CompileError::InInclude { path, include, error } => {
+                write!(f, "{}: in {}:{}", include, path, error)
+            },
+            CompileError::InMacro { name, invocation, error } => {
+                write!(f, "{} (in the expansion of macro {} at {})", error, name, invocation)
+            },
+        }
+    }
+}
+impl std::error::Error for CompileError {}
+
+/// Kind of operand, as written in error messages
+fn describe(kind: OperandKind) -> &'static str {
+    match kind {
+        OperandKind::Register => "an integer register",
+        OperandKind::FloatRegister => "a float register",
+        OperandKind::Immediate32 => "an immediate",
+        OperandKind::Float64 => "a float",
+    }
+}
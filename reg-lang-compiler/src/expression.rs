@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use pest::iterators::Pair;
+
+use crate::{parse_number, unexpected, CompileError, Rule, Span};
+
+/// Constant expression of an immediate (`#SIZE * 4 + 1`) or of a `.equ` directive
+#[derive(Debug, Clone)]
+pub(crate) struct Expression {
+    /// Source of the expression, used in error messages
+    text: String,
+    span: Span,
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Number(i64),
+    /// Label or constant
+    Name { name: String, span: Span },
+    Negate(Box<Node>),
+    Binary { operator: Operator, left: Box<Node>, right: Box<Node> },
+}
+
+/// Why a node couldn't be evaluated
+enum Failure {
+    /// Error returned by the lookup of a name
+    Lookup(CompileError),
+    Overflow,
+    DivisionByZero,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+impl Expression {
+    /// Builds the expression of an `IMMEDIATE` or an `EXPRESSION` pair
+    pub(crate) fn parse(pair: &Pair<Rule>) -> Result<Self, CompileError> {
+        let expression = match pair.as_rule() {
+            Rule::IMMEDIATE => pair.clone().into_inner().next().expect("IMMEDIATE always holds an EXPRESSION"),
+            _ => pair.clone(),
+        };
+        Ok(Self {
+            text: pair.as_str().to_string(),
+            span: Span::of(pair),
+            root: Node::parse(expression)?,
+        })
+    }
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+    pub(crate) fn span(&self) -> Span {
+        self.span
+    }
+    /// Returns true if the expression is a single hexadecimal or binary literal, written as the bits of an immediate
+    pub(crate) fn is_bit_pattern(&self) -> bool {
+        let literal = self.text.trim_start_matches('#');
+        let prefix = literal.get(..2).unwrap_or_default();
+        matches!(self.root, Node::Number(_)) && (prefix.eq_ignore_ascii_case("0x") || prefix.eq_ignore_ascii_case("0b"))
+    }
+    /// Returns true if every name used by the expression is accepted by `known`
+    pub(crate) fn only_uses(&self, known: impl Fn(&str) -> bool) -> bool {
+        self.root.only_uses(&known)
+    }
+    /// Replaces the names found in `names` by their new name, used for the local labels of a macro
+    pub(crate) fn rename(&mut self, names: &HashMap<String, String>) {
+        self.root.rename(names);
+    }
+    /// Coefficient of every address used by the expression, to relocate it in an object file.
+    /// Returns `None` when an address is multiplied or divided, the linker couldn't relocate the result
+    pub(crate) fn addresses(&self, is_address: impl Fn(&str) -> bool) -> Option<HashMap<String, i64>> {
+        let mut addresses = HashMap::new();
+        self.root.addresses(&is_address, 1, &mut addresses)?;
+        Some(addresses)
+    }
+    /// Computes the value of the expression, `lookup` gives the value of a name or the error of an undefined one
+    pub(crate) fn evaluate(&self, lookup: impl Fn(&str, Span) -> Result<i64, CompileError>) -> Result<i64, CompileError> {
+        self.root.evaluate(&lookup).map_err(|failure| match failure {
+            Failure::Lookup(error) => error,
+            Failure::Overflow => CompileError::ExpressionOverflow {
+                expression: self.text.clone(),
+                span: self.span,
+            },
+            Failure::DivisionByZero => CompileError::DivisionByZero {
+                expression: self.text.clone(),
+                span: self.span,
+            },
+        })
+    }
+}
+
+impl Node {
+    fn parse(pair: Pair<Rule>) -> Result<Self, CompileError> {
+        match pair.as_rule() {
+            Rule::EXPRESSION | Rule::PRODUCT => {
+                let mut inner = pair.into_inner();
+                let mut node = Node::parse(inner.next().expect("an expression starts with an operand"))?;
+                while let Some(operator) = inner.next() {
+                    let operator = match operator.as_rule() {
+                        Rule::PLUS => Operator::Add,
+                        Rule::MINUS => Operator::Subtract,
+                        Rule::TIMES => Operator::Multiply,
+                        Rule::DIVIDE => Operator::Divide,
+                        Rule::REMAINDER => Operator::Remainder,
+                        rule => return Err(unexpected(rule, &operator)),
+                    };
+                    let right = Node::parse(inner.next().expect("an operator is followed by an operand"))?;
+                    node = Node::Binary { operator, left: Box::new(node), right: Box::new(right) };
+                }
+                Ok(node)
+            },
+            Rule::NEGATION => {
+                let operand = pair.into_inner().next().expect("NEGATION always holds an operand");
+                Ok(Node::Negate(Box::new(Node::parse(operand)?)))
+            },
+            Rule::INTEGER => {
+                let number = parse_number(pair.as_str()).ok_or_else(|| CompileError::ImmediateOverflow {
+                    value: pair.as_str().to_string(),
+                    span: Span::of(&pair),
+                })?;
+                Ok(Node::Number(number))
+            },
+            Rule::IDENT => Ok(Node::Name {
+                name: pair.as_str().to_string(),
+                span: Span::of(&pair),
+            }),
+            rule => Err(unexpected(rule, &pair)),
+        }
+    }
+    fn only_uses(&self, known: &impl Fn(&str) -> bool) -> bool {
+        match self {
+            Node::Number(_) => true,
+            Node::Name { name, .. } => known(name),
+            Node::Negate(operand) => operand.only_uses(known),
+            Node::Binary { left, right, .. } => left.only_uses(known) && right.only_uses(known),
+        }
+    }
+    fn rename(&mut self, names: &HashMap<String, String>) {
+        match self {
+            Node::Number(_) => {},
+            Node::Name { name, .. } => {
+                if let Some(renamed) = names.get(name) {
+                    *name = renamed.clone();
+                }
+            },
+            Node::Negate(operand) => operand.rename(names),
+            Node::Binary { left, right, .. } => {
+                left.rename(names);
+                right.rename(names);
+            },
+        }
+    }
+    fn addresses(&self, is_address: &impl Fn(&str) -> bool, sign: i64, addresses: &mut HashMap<String, i64>) -> Option<()> {
+        match self {
+            Node::Number(_) => Some(()),
+            Node::Name { name, .. } => {
+                if is_address(name) {
+                    *addresses.entry(name.clone()).or_default() += sign;
+                }
+                Some(())
+            },
+            Node::Negate(operand) => operand.addresses(is_address, -sign, addresses),
+            Node::Binary { operator: Operator::Add, left, right } => {
+                left.addresses(is_address, sign, addresses)?;
+                right.addresses(is_address, sign, addresses)
+            },
+            Node::Binary { operator: Operator::Subtract, left, right } => {
+                left.addresses(is_address, sign, addresses)?;
+                right.addresses(is_address, -sign, addresses)
+            },
+            Node::Binary { left, right, .. } => {
+                let constant = |name: &str| !is_address(name);
+                (left.only_uses(&constant) && right.only_uses(&constant)).then_some(())
+            },
+        }
+    }
+    /// Value of the node, computed on 64 bits
+    fn evaluate(&self, lookup: &impl Fn(&str, Span) -> Result<i64, CompileError>) -> Result<i64, Failure> {
+        match self {
+            Node::Number(number) => Ok(*number),
+            Node::Name { name, span } => lookup(name, *span).map_err(Failure::Lookup),
+            Node::Negate(operand) => operand.evaluate(lookup)?.checked_neg().ok_or(Failure::Overflow),
+            Node::Binary { operator, left, right } => {
+                let left = left.evaluate(lookup)?;
+                let right = right.evaluate(lookup)?;
+                let value = match operator {
+                    Operator::Add => left.checked_add(right),
+                    Operator::Subtract => left.checked_sub(right),
+                    Operator::Multiply => left.checked_mul(right),
+                    Operator::Divide | Operator::Remainder if right == 0 => return Err(Failure::DivisionByZero),
+                    Operator::Divide => left.checked_div(right),
+                    Operator::Remainder => left.checked_rem(right),
+                };
+                value.ok_or(Failure::Overflow)
+            },
+        }
+    }
+}
@@ -2,8 +2,22 @@ extern crate pest;
 #[macro_use]
 extern crate pest_derive;
 
+mod error;
+mod expression;
+mod macros;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
 use pest::Parser;
-use reg_byte::OpCode;
+use pest::iterators::Pair;
+use reg_byte::object::{ObjectFile, Relocation, Symbol};
+use reg_byte::{OpCode, Operand, OperandKind, REGISTER_COUNT};
+
+pub use error::{CompileError, Span};
+use expression::Expression;
+use macros::{Argument, Macro, Scope};
 
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
@@ -12,302 +26,653 @@ struct RegParser;
 pub struct RegCompiler {
     pub program: Vec<u8>,
     pub program_counter: usize,
+    /// Byte offset of every label defined in the program
+    pub labels: HashMap<String, usize>,
+    /// Value of every constant defined with `.equ`
+    pub constants: HashMap<String, i64>,
+    /// Register named by every alias defined with `.alias` in the file being assembled
+    aliases: HashMap<String, Operand>,
+    /// Immediates referring to a label or a constant defined later, patched once every name is known
+    patches: Vec<ImmediatePatch>,
+    /// Number of macro expansions so far, used to make the labels of every expansion unique
+    expansions: usize,
+    /// Labels of other modules declared with `.extern`
+    externs: HashSet<String>,
+    /// Labels exported with `.global`
+    globals: Vec<(String, Span)>,
+    /// True when assembling an object file, the addresses are then relocated by the linker
+    relocatable: bool,
+    /// Immediates of an object file holding an address
+    relocations: Vec<Relocation>,
+    /// Labels of other modules used by an object file
+    imports: Vec<String>,
+}
+
+/// State of the assembly borrowing the sources
+struct Context<'i> {
+    macros: HashMap<String, Macro<'i>>,
+    /// Source of every included file, read before assembling
+    sources: &'i HashMap<PathBuf, String>,
+    /// Directory of the file being assembled, `.include` paths are relative to it
+    directory: PathBuf,
+}
+
+/// Location of an immediate waiting for the names of its expression
+#[derive(Debug)]
+struct ImmediatePatch {
+    offset: usize,
+    kind: OperandKind,
+    expression: Expression,
+    /// Macros being expanded when the immediate was assembled, to report errors at their invocation
+    expansions: Vec<(String, Span)>,
 }
 impl RegCompiler {
-    pub fn compile(input :&str) -> RegCompiler {
+    pub fn compile(input :&str) -> Result<RegCompiler, CompileError> {
+        RegCompiler::compile_in(input, Path::new(""))
+    }
+    /// Same as `compile`, with the paths of `.include` directives relative to `directory`
+    pub fn compile_in(input: &str, directory: &Path) -> Result<RegCompiler, CompileError> {
+        RegCompiler::assemble(input, directory, false)
+    }
+    /// Assembles a module into an object file. The labels declared with `.extern` are left to the linker,
+    /// and the labels declared with `.global` are exported to the other modules
+    pub fn compile_object(input: &str, directory: &Path) -> Result<ObjectFile, CompileError> {
+        let compiler = RegCompiler::assemble(input, directory, true)?;
+        let mut exports = Vec::with_capacity(compiler.globals.len());
+        for (name, span) in &compiler.globals {
+            let offset = compiler.labels.get(name).ok_or_else(|| CompileError::UndefinedLabel {
+                label: name.clone(),
+                span: *span,
+            })?;
+            exports.push(Symbol {
+                name: name.clone(),
+                offset: *offset as u32,
+            });
+        }
+        Ok(ObjectFile {
+            code: compiler.program,
+            exports,
+            imports: compiler.imports,
+            relocations: compiler.relocations,
+        })
+    }
+    fn assemble(input: &str, directory: &Path, relocatable: bool) -> Result<RegCompiler, CompileError> {
+        let mut sources = HashMap::new();
+        read_includes(input, directory, &mut Vec::new(), &mut sources)?;
         let mut compiler = RegCompiler {
             program: vec![],
             program_counter: 0,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            patches: vec![],
+            expansions: 0,
+            externs: HashSet::new(),
+            globals: vec![],
+            relocatable,
+            relocations: vec![],
+            imports: vec![],
+        };
+        let mut context = Context {
+            macros: HashMap::new(),
+            sources: &sources,
+            directory: directory.to_path_buf(),
         };
-        let program = RegParser::parse(Rule::program, input).unwrap_or_else(|e| panic!("{}", e));
+        compiler.compile_program(input, &mut context)?;
+        compiler.resolve_labels()?;
+        Ok(compiler)
+    }
+    /// Assembles every line of a file
+    fn compile_program<'i>(&mut self, input: &'i str, context: &mut Context<'i>) -> Result<(), CompileError> {
+        let program = RegParser::parse(Rule::program, input)?;
         for expr in program.into_iter() {
             match expr.as_rule() {
                 Rule::program => {
-                    for instruction in expr.into_inner() {
-                        match instruction.as_rule() {
-                            Rule::STORE => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::STORE as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        Rule::NUMBERS => {
-                                            let number = args.as_str().replace("#", "").parse::<u16>().unwrap();
-                                            compiler.program.push((number >> 8) as u8);
-                                            compiler.program.push((number & 0xFF) as u8);
-                                        }
-                                        _ => {
-                                            panic!("Invalid rule (STORE)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::ADD => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::ADD as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (ADD)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::MUL => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::MUL as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (MUL)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::DIV => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::DIV as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (DIV)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::SUB => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::SUB as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (SUB)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::JMP => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::JMP as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (JMP)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::JMPB => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::JMPB as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (JMPB)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::JMPF => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::JMPF as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (JMPF)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::EQ => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::EQ as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (EQ)");
-                                        }
-                                    }
-                                }
-                                compiler.program.push(0);
-                            },
-                            Rule::NEQ => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::NEQ as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (NEQ)");
-                                        }
-                                    }
-                                }
-                                compiler.program.push(0);
-                            },
-                            Rule::GT => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::GT as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (GT)");
-                                        }
-                                    }
-                                }
-                                compiler.program.push(0);
-                            },
-                            Rule::LT => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::LT as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (LT)");
-                                        }
-                                    }
-                                }
-                                compiler.program.push(0);
-                            },
-                            Rule::GTE => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::GTE as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (GTE)");
-                                        }
-                                    }
-                                }
-                                compiler.program.push(0);
-                            },
-                            Rule::LTE => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::LTE as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (LTE)");
-                                        }
-                                    }
-                                }
-                                compiler.program.push(0);
-                            },
-                            Rule::JMPE => {
-                            compiler.program_counter += 1;
-                                compiler.program.push(OpCode::JMPE as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (JMPE)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::PRINT => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::PRINT as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (PRINT)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::PRINTLN => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::PRINTLN as u8);
-                                for args in instruction.into_inner() {
-                                    match args.as_rule() {
-                                        Rule::REGISTER => {
-                                            let register = args.as_str().replace("$", "");
-                                            compiler.program.push(register.parse::<u8>().unwrap());
-                                        },
-                                        _ => {
-                                            panic!("Invalid rule (PRINTLN)");
-                                        }
-                                    }
-                                }
-                            },
-                            Rule::HLT => {
-                                compiler.program_counter += 1;
-                                compiler.program.push(OpCode::HLT as u8);
-                            },
-                            Rule::EOI => {
-                            } ,
-                            _ => {
-                                panic!("Invalid rule (instruction)");
-                            }
-                        }
+                    for statement in expr.into_inner() {
+                        self.compile_statement(statement, context, &mut Scope::default())?;
                     }
                 }
-                _ => {
-                    panic!("Invalid rule (program)");
-                }
+                rule => return Err(unexpected(rule, &expr)),
             }
         }
-        return compiler;
+        Ok(())
+    }
+    /// Assembles a label, a directive or an instruction, `scope` holds the names of the macro expansion it belongs to
+    fn compile_statement<'i>(&mut self, statement: Pair<'i, Rule>, context: &mut Context<'i>, scope: &mut Scope) -> Result<(), CompileError> {
+        let opcode = match statement.as_rule() {
+            Rule::LABEL => return self.define_label(&statement, scope),
+            Rule::EQU => return self.define_constant(&statement),
+            Rule::ALIAS => return self.define_alias(&statement, scope),
+            Rule::MACRO => return define_macro(&statement, &mut context.macros),
+            Rule::MACRO_CALL => return self.expand_macro(statement, context, scope),
+            Rule::INCLUDE => return self.include(&statement, context),
+            Rule::GLOBAL => {
+                self.export(&statement);
+                return Ok(());
+            },
+            Rule::EXTERN => return self.define_extern(&statement),
+            Rule::EOI => return Ok(()),
+            Rule::STORE => OpCode::STORE,
+            Rule::ADD => OpCode::ADD,
+            Rule::MUL => OpCode::MUL,
+            Rule::DIV => OpCode::DIV,
+            Rule::SUB => OpCode::SUB,
+            Rule::JMP => OpCode::JMP,
+            Rule::JMPB => OpCode::JMPB,
+            Rule::JMPF => OpCode::JMPF,
+            Rule::EQ => OpCode::EQ,
+            Rule::NEQ => OpCode::NEQ,
+            Rule::GT => OpCode::GT,
+            Rule::LT => OpCode::LT,
+            Rule::GTE => OpCode::GTE,
+            Rule::LTE => OpCode::LTE,
+            Rule::JMPE => OpCode::JMPE,
+            Rule::PRINT => OpCode::PRINT,
+            Rule::PRINTLN => OpCode::PRINTLN,
+            Rule::CALL => OpCode::CALL,
+            Rule::RET => OpCode::RET,
+            Rule::STACK_PUSH => OpCode::PUSH,
+            Rule::STACK_POP => OpCode::POP,
+            Rule::LDB => OpCode::LDB,
+            Rule::LDH => OpCode::LDH,
+            Rule::LDBS => OpCode::LDBS,
+            Rule::LDHS => OpCode::LDHS,
+            Rule::LDW => OpCode::LDW,
+            Rule::STB => OpCode::STB,
+            Rule::STH => OpCode::STH,
+            Rule::STW => OpCode::STW,
+            Rule::ALOC => OpCode::ALOC,
+            Rule::READ => OpCode::READ,
+            Rule::READC => OpCode::READC,
+            Rule::DIVU => OpCode::DIVU,
+            Rule::GTU => OpCode::GTU,
+            Rule::LTU => OpCode::LTU,
+            Rule::GTEU => OpCode::GTEU,
+            Rule::LTEU => OpCode::LTEU,
+            Rule::LDD => OpCode::LDD,
+            Rule::STD => OpCode::STD,
+            Rule::AND => OpCode::AND,
+            Rule::OR => OpCode::OR,
+            Rule::XOR => OpCode::XOR,
+            Rule::NOT => OpCode::NOT,
+            Rule::SHL => OpCode::SHL,
+            Rule::SHR => OpCode::SHR,
+            Rule::SAR => OpCode::SAR,
+            Rule::MOD => OpCode::MOD,
+            Rule::GETREM => OpCode::GETREM,
+            Rule::GETFLAG => OpCode::GETFLAG,
+            Rule::JMPNE => OpCode::JMPNE,
+            Rule::MOV => OpCode::MOV,
+            Rule::INC => OpCode::INC,
+            Rule::DEC => OpCode::DEC,
+            Rule::NEG => OpCode::NEG,
+            Rule::FSTORE => OpCode::FSTORE,
+            Rule::FADD => OpCode::FADD,
+            Rule::FSUB => OpCode::FSUB,
+            Rule::FMUL => OpCode::FMUL,
+            Rule::FDIV => OpCode::FDIV,
+            Rule::FEQ => OpCode::FEQ,
+            Rule::FNEQ => OpCode::FNEQ,
+            Rule::FGT => OpCode::FGT,
+            Rule::FLT => OpCode::FLT,
+            Rule::FGTE => OpCode::FGTE,
+            Rule::FLTE => OpCode::FLTE,
+            Rule::ITOF => OpCode::ITOF,
+            Rule::FTOI => OpCode::FTOI,
+            Rule::FPRINT => OpCode::FPRINT,
+            Rule::FPRINTLN => OpCode::FPRINTLN,
+            Rule::HLT => OpCode::HLT,
+            rule => return Err(unexpected(rule, &statement)),
+        };
+        self.program_counter += 1;
+        self.program.push(opcode as u8);
+        let layout = opcode.operands();
+        let span = Span::of(&statement);
+        let mut count = 0;
+        for args in statement.into_inner() {
+            let kind = *layout.get(count).ok_or_else(|| unexpected(args.as_rule(), &args))?;
+            self.push_operand(&args, kind, scope)?;
+            count += 1;
+        }
+        if count != layout.len() {
+            return Err(CompileError::Parse {
+                message: format!("{} expects {} operands", opcode.mnemonic(), layout.len()),
+                span,
+            });
+        }
+        Ok(())
+    }
+    /// Encodes an operand of the kind expected by the instruction
+    fn push_operand(&mut self, args: &Pair<Rule>, kind: OperandKind, scope: &Scope) -> Result<(), CompileError> {
+        match (args.as_rule(), kind) {
+            (Rule::REGISTER, OperandKind::Register) => self.push_register(args),
+            (Rule::ALIAS_REF, OperandKind::Register | OperandKind::FloatRegister) => self.push_alias(args, kind, scope),
+            (Rule::IMMEDIATE, OperandKind::Immediate32 | OperandKind::Float64) => self.push_immediate(scope.expression(args)?, kind, scope),
+            (Rule::FLOAT_REGISTER, OperandKind::FloatRegister) => self.push_register(args),
+            (Rule::FLOAT, OperandKind::Float64) => {
+                parse_float(args)?.encode(&mut self.program);
+                Ok(())
+            },
+            (Rule::MACRO_PARAM, _) => self.push_argument(args, kind, scope),
+            (rule, _) => Err(unexpected(rule, args)),
+        }
+    }
+    /// Assembles the body of a macro with the arguments of its invocation (`name $1 #10`), its labels get a name unique to the expansion
+    fn expand_macro<'i>(&mut self, invocation: Pair<'i, Rule>, context: &mut Context<'i>, scope: &mut Scope) -> Result<(), CompileError> {
+        let span = Span::of(&invocation);
+        let mut inner = invocation.into_inner();
+        let name = inner.next().expect("MACRO_CALL always starts with a MNEMONIC");
+        let definition = match context.macros.get(name.as_str()) {
+            Some(definition) => definition.clone(),
+            None => {
+                return Err(CompileError::UnknownMnemonic {
+                    mnemonic: name.as_str().to_string(),
+                    span: Span::of(&name),
+                });
+            },
+        };
+        if scope.expansions.iter().any(|(expanding, _)| expanding == name.as_str()) {
+            return Err(CompileError::MacroRecursion {
+                name: name.as_str().to_string(),
+                span,
+            });
+        }
+        let arguments = inner.map(|argument| self.argument(&argument, scope)).collect::<Result<Vec<_>, _>>()?;
+        if arguments.len() != definition.parameters.len() {
+            return Err(CompileError::MacroArguments {
+                name: name.as_str().to_string(),
+                expected: definition.parameters.len(),
+                found: arguments.len(),
+                span,
+            });
+        }
+        self.expansions += 1;
+        let mut expansion = Scope {
+            arguments: definition.parameters.iter().cloned().zip(arguments).collect(),
+            labels: definition.labels.iter().map(|label| (label.clone(), format!("{}@{}", label, self.expansions))).collect(),
+            aliases: HashMap::new(),
+            expansions: scope.expansions.iter().cloned().chain([(name.as_str().to_string(), span)]).collect(),
+        };
+        for statement in definition.body.into_inner() {
+            self.compile_statement(statement, context, &mut expansion).map_err(|error| CompileError::InMacro {
+                name: name.as_str().to_string(),
+                invocation: span,
+                error: Box::new(error),
+            })?;
+        }
+        Ok(())
+    }
+    /// Assembles the file of an `.include "path"` directive in place of the directive.
+    /// Aliases belong to the file defining them, the included file starts without any and its own are dropped at its end
+    fn include(&mut self, directive: &Pair<Rule>, context: &mut Context) -> Result<(), CompileError> {
+        let (literal, path) = include_path(directive, &context.directory)?;
+        let sources = context.sources;
+        let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let directory = std::mem::replace(&mut context.directory, directory);
+        let aliases = std::mem::take(&mut self.aliases);
+        let result = self.compile_program(&sources[&path], context);
+        self.aliases = aliases;
+        context.directory = directory;
+        result.map_err(|error| CompileError::InInclude {
+            path: literal,
+            include: Span::of(directive),
+            error: Box::new(error),
+        })
+    }
+    /// Exports a label to the other modules (`.global name`)
+    fn export(&mut self, directive: &Pair<Rule>) {
+        let name = directive.clone().into_inner().next().expect("GLOBAL always holds an IDENT");
+        if !self.globals.iter().any(|(global, _)| global == name.as_str()) {
+            self.globals.push((name.as_str().to_string(), Span::of(&name)));
+        }
+    }
+    /// Declares a label of another module (`.extern name`), which the linker resolves
+    fn define_extern(&mut self, directive: &Pair<Rule>) -> Result<(), CompileError> {
+        let name = directive.clone().into_inner().next().expect("EXTERN always holds an IDENT");
+        if self.labels.contains_key(name.as_str()) || self.constants.contains_key(name.as_str()) {
+            return Err(CompileError::DuplicateLabel {
+                label: name.as_str().to_string(),
+                span: Span::of(&name),
+            });
+        }
+        self.externs.insert(name.as_str().to_string());
+        Ok(())
+    }
+    /// Resolves the operand given to a macro parameter, in the scope of the invocation
+    fn argument(&self, pair: &Pair<Rule>, scope: &Scope) -> Result<Argument, CompileError> {
+        match pair.as_rule() {
+            Rule::REGISTER | Rule::FLOAT_REGISTER => Ok(Argument::Operand(parse_register(pair)?)),
+            Rule::ALIAS_REF => Ok(Argument::Operand(self.resolve_alias(pair, scope)?)),
+            Rule::FLOAT => Ok(Argument::Operand(parse_float(pair)?)),
+            Rule::IMMEDIATE => Ok(Argument::Immediate(scope.expression(pair)?)),
+            Rule::MACRO_PARAM => scope.argument(pair).cloned(),
+            rule => Err(unexpected(rule, pair)),
+        }
+    }
+    /// Encodes the argument of a macro parameter (`\value`), which must be of the expected kind
+    fn push_argument(&mut self, pair: &Pair<Rule>, kind: OperandKind, scope: &Scope) -> Result<(), CompileError> {
+        match scope.argument(pair)? {
+            Argument::Operand(operand) if operand.kind() == kind => {
+                operand.encode(&mut self.program);
+                Ok(())
+            },
+            Argument::Immediate(expression) if matches!(kind, OperandKind::Immediate32 | OperandKind::Float64) => {
+                self.push_immediate(expression.clone(), kind, scope)
+            },
+            argument => Err(CompileError::WrongArgument {
+                parameter: pair.as_str().to_string(),
+                argument: argument.text(),
+                expected: kind,
+                span: Span::of(pair),
+            }),
+        }
     }
-}
\ No newline at end of file
+    /// Binds the label to the offset of the next instruction, a label of a macro gets the name unique to its expansion
+    fn define_label(&mut self, label: &Pair<Rule>, scope: &Scope) -> Result<(), CompileError> {
+        let name = label.as_str().trim_end_matches(':');
+        let name = scope.labels.get(name).map_or(name, String::as_str);
+        if self.labels.contains_key(name) || self.constants.contains_key(name) || self.externs.contains(name) {
+            return Err(CompileError::DuplicateLabel {
+                label: name.to_string(),
+                span: Span::of(label),
+            });
+        }
+        self.labels.insert(name.to_string(), self.program.len());
+        Ok(())
+    }
+    /// Binds a constant to the value of its expression (`.equ NAME expression`), which can only use constants defined before
+    fn define_constant(&mut self, directive: &Pair<Rule>) -> Result<(), CompileError> {
+        let mut inner = directive.clone().into_inner();
+        let name = inner.next().expect("EQU always starts with an IDENT");
+        let expression = Expression::parse(&inner.next().expect("EQU always ends with an EXPRESSION"))?;
+        if self.labels.contains_key(name.as_str()) || self.constants.contains_key(name.as_str()) || self.externs.contains(name.as_str()) {
+            return Err(CompileError::DuplicateConstant {
+                constant: name.as_str().to_string(),
+                span: Span::of(&name),
+            });
+        }
+        let value = expression.evaluate(|constant, span| {
+            self.constants.get(constant).copied().ok_or_else(|| CompileError::UndefinedConstant {
+                constant: constant.to_string(),
+                span,
+            })
+        })?;
+        self.constants.insert(name.as_str().to_string(), value);
+        Ok(())
+    }
+    /// Encodes an immediate operand (`#N`, `#name` or `#SIZE * 4 + 1`).
+    /// An expression using labels or constants that aren't defined yet is reserved and filled by `resolve_labels`
+    fn push_immediate(&mut self, expression: Expression, kind: OperandKind, scope: &Scope) -> Result<(), CompileError> {
+        if expression.only_uses(|name| self.constants.contains_key(name)) {
+            let value = expression.evaluate(|name, _| Ok(self.constants[name]))?;
+            immediate_operand(kind, value, &expression)?.encode(&mut self.program);
+        } else {
+            self.patches.push(ImmediatePatch {
+                offset: self.program.len(),
+                kind,
+                expression,
+                expansions: scope.expansions.clone(),
+            });
+            self.program.resize(self.program.len() + kind.width(), 0);
+        }
+        Ok(())
+    }
+    /// Writes the value of every reserved immediate, now that every label is known
+    fn resolve_labels(&mut self) -> Result<(), CompileError> {
+        for patch in std::mem::take(&mut self.patches) {
+            let value = patch.expression.evaluate(|name, span| {
+                let value = self.constants.get(name).copied().or_else(|| self.labels.get(name).map(|address| *address as i64));
+                // The linker adds the address of an imported label
+                let value = value.or_else(|| (self.relocatable && self.externs.contains(name)).then_some(0));
+                value.ok_or_else(|| CompileError::UndefinedLabel {
+                    label: name.to_string(),
+                    span,
+                })
+            });
+            let operand = value.and_then(|value| immediate_operand(patch.kind, value, &patch.expression));
+            let operand = operand.map_err(|error| in_expansions(error, &patch.expansions))?;
+            if self.relocatable {
+                let relocation = self.relocation(&patch).map_err(|error| in_expansions(error, &patch.expansions))?;
+                self.relocations.extend(relocation);
+            }
+            let mut encoded = Vec::with_capacity(patch.kind.width());
+            operand.encode(&mut encoded);
+            self.program[patch.offset..patch.offset + encoded.len()].copy_from_slice(&encoded);
+        }
+        Ok(())
+    }
+    /// Relocation of an immediate of an object file, the expression can only add an offset to a label of the module or to an imported label
+    fn relocation(&mut self, patch: &ImmediatePatch) -> Result<Option<Relocation>, CompileError> {
+        let addresses = patch.expression.addresses(|name| self.labels.contains_key(name) || self.externs.contains(name));
+        let mut local = 0;
+        let mut imported = Vec::new();
+        for (name, coefficient) in addresses.iter().flatten() {
+            if self.labels.contains_key(name) {
+                local += coefficient;
+            } else if *coefficient != 0 {
+                imported.push((name.as_str(), *coefficient));
+            }
+        }
+        let offset = patch.offset as u32;
+        match (addresses.is_some(), patch.kind, local, imported.as_slice()) {
+            (true, _, 0, []) => Ok(None),
+            (true, OperandKind::Immediate32, 1, []) => Ok(Some(Relocation::Local { offset })),
+            (true, OperandKind::Immediate32, 0, [(name, 1)]) => {
+                let import = match self.imports.iter().position(|import| import == name) {
+                    Some(index) => index,
+                    None => {
+                        self.imports.push(name.to_string());
+                        self.imports.len() - 1
+                    },
+                };
+                Ok(Some(Relocation::Import { offset, import: import as u32 }))
+            },
+            _ => Err(CompileError::NotRelocatable {
+                expression: patch.expression.text().to_string(),
+                span: patch.expression.span(),
+            }),
+        }
+    }
+    /// Encodes a register operand (`$N`) or a float register operand (`$fN`)
+    fn push_register(&mut self, args: &Pair<Rule>) -> Result<(), CompileError> {
+        parse_register(args)?.encode(&mut self.program);
+        Ok(())
+    }
+    /// Binds a name to a register (`.alias counter $3`), the alias is then used as `$counter`.
+    /// An alias defined by a macro body only exists in its expansion
+    fn define_alias(&mut self, directive: &Pair<Rule>, scope: &mut Scope) -> Result<(), CompileError> {
+        let mut inner = directive.clone().into_inner();
+        let name = inner.next().expect("ALIAS always starts with an IDENT");
+        let register = parse_register(&inner.next().expect("ALIAS always ends with a register"))?;
+        if is_float_register_name(name.as_str()) {
+            return Err(CompileError::AliasShadowsRegister {
+                alias: name.as_str().to_string(),
+                span: Span::of(&name),
+            });
+        }
+        if self.aliases.contains_key(name.as_str()) || scope.aliases.contains_key(name.as_str()) {
+            return Err(CompileError::DuplicateAlias {
+                alias: name.as_str().to_string(),
+                span: Span::of(&name),
+            });
+        }
+        if scope.in_macro() {
+            scope.aliases.insert(name.as_str().to_string(), register);
+        } else {
+            self.aliases.insert(name.as_str().to_string(), register);
+        }
+        Ok(())
+    }
+    /// Register named by an alias (`$counter`), the aliases of the macro expansion come first
+    fn resolve_alias(&self, args: &Pair<Rule>, scope: &Scope) -> Result<Operand, CompileError> {
+        let name = &args.as_str()[1..];
+        match scope.aliases.get(name).or_else(|| self.aliases.get(name)) {
+            Some(register) => Ok(*register),
+            // `$f1` where an integer register is expected
+            None if is_float_register_name(name) => Err(CompileError::WrongRegisterKind {
+                register: args.as_str().to_string(),
+                expected: OperandKind::Register,
+                span: Span::of(args),
+            }),
+            None => Err(CompileError::UndefinedAlias {
+                alias: args.as_str().to_string(),
+                span: Span::of(args),
+            }),
+        }
+    }
+    /// Encodes the register named by an alias (`$counter`), which must be of the expected kind
+    fn push_alias(&mut self, args: &Pair<Rule>, kind: OperandKind, scope: &Scope) -> Result<(), CompileError> {
+        let register = self.resolve_alias(args, scope)?;
+        if register.kind() != kind {
+            return Err(CompileError::WrongRegisterKind {
+                register: format!("{} ({})", args.as_str(), register),
+                expected: kind,
+                span: Span::of(args),
+            });
+        }
+        register.encode(&mut self.program);
+        Ok(())
+    }
+}
+
+/// Registers the macro of a `.macro` directive, which can then be invoked like an instruction
+fn define_macro<'i>(directive: &Pair<'i, Rule>, macros: &mut HashMap<String, Macro<'i>>) -> Result<(), CompileError> {
+    let (name, definition) = Macro::parse(directive)?;
+    let is_instruction = (0..OpCode::ILG as u8).any(|opcode| OpCode::from(opcode).mnemonic().eq_ignore_ascii_case(name.as_str()));
+    if is_instruction {
+        return Err(CompileError::MacroShadowsInstruction {
+            name: name.as_str().to_string(),
+            span: Span::of(&name),
+        });
+    }
+    if macros.contains_key(name.as_str()) {
+        return Err(CompileError::DuplicateMacro {
+            name: name.as_str().to_string(),
+            span: Span::of(&name),
+        });
+    }
+    macros.insert(name.as_str().to_string(), definition);
+    Ok(())
+}
+
+/// Reads the files included by `input` and the files they include, `including` holds the files being read to detect cycles
+fn read_includes(input: &str, directory: &Path, including: &mut Vec<PathBuf>, sources: &mut HashMap<PathBuf, String>) -> Result<(), CompileError> {
+    let program = RegParser::parse(Rule::program, input)?;
+    for directive in program.flatten().filter(|pair| pair.as_rule() == Rule::INCLUDE) {
+        let (literal, path) = include_path(&directive, directory)?;
+        if including.contains(&path) {
+            return Err(CompileError::IncludeCycle {
+                path: literal,
+                span: Span::of(&directive),
+            });
+        }
+        if sources.contains_key(&path) {
+            continue;
+        }
+        let source = fs::read_to_string(&path).map_err(|e| CompileError::IncludeFailed {
+            path: literal.clone(),
+            message: e.to_string(),
+            span: Span::of(&directive),
+        })?;
+        including.push(path.clone());
+        let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        read_includes(&source, &parent, including, sources).map_err(|error| CompileError::InInclude {
+            path: literal,
+            include: Span::of(&directive),
+            error: Box::new(error),
+        })?;
+        including.pop();
+        sources.insert(path, source);
+    }
+    Ok(())
+}
+
+/// Path of an `.include` directive as written, and the canonical path of the file it includes
+fn include_path(directive: &Pair<Rule>, directory: &Path) -> Result<(String, PathBuf), CompileError> {
+    let string = directive.clone().into_inner().next().expect("INCLUDE always holds a STRING");
+    let literal = string.into_inner().next().expect("STRING always holds its content").as_str().to_string();
+    let path = fs::canonicalize(directory.join(&literal)).map_err(|e| CompileError::IncludeFailed {
+        path: literal.clone(),
+        message: e.to_string(),
+        span: Span::of(directive),
+    })?;
+    Ok((literal, path))
+}
+
+/// Parses a float operand (`#N.N`)
+fn parse_float(pair: &Pair<Rule>) -> Result<Operand, CompileError> {
+    let literal = pair.as_str().replace('#', "");
+    let number = literal.parse::<f64>().ok().filter(|number| number.is_finite()).ok_or_else(|| CompileError::FloatOverflow {
+        value: pair.as_str().to_string(),
+        span: Span::of(pair),
+    })?;
+    Ok(Operand::Float(number))
+}
+
+/// Wraps an error raised in the body of nested macros so it also points at their invocations, outermost last
+fn in_expansions(error: CompileError, expansions: &[(String, Span)]) -> CompileError {
+    expansions.iter().rev().fold(error, |error, (name, invocation)| CompileError::InMacro {
+        name: name.clone(),
+        invocation: *invocation,
+        error: Box::new(error),
+    })
+}
+
+/// Parses a `REGISTER` (`$N`) or a `FLOAT_REGISTER` (`$fN`) pair, which must be one of the VM registers
+fn parse_register(pair: &Pair<Rule>) -> Result<Operand, CompileError> {
+    let (index, operand): (_, fn(u8) -> Operand) = match pair.as_rule() {
+        Rule::FLOAT_REGISTER => (&pair.as_str()[2..], Operand::FloatRegister),
+        _ => (&pair.as_str()[1..], Operand::Register),
+    };
+    match index.parse::<u8>() {
+        Ok(index) if (index as usize) < REGISTER_COUNT => Ok(operand(index)),
+        _ => Err(CompileError::RegisterOutOfRange {
+            register: pair.as_str().to_string(),
+            span: Span::of(pair),
+        }),
+    }
+}
+
+/// Returns true if `name` reads as a float register once prefixed with `$`, like `f1`
+fn is_float_register_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some('f' | 'F')) && !chars.as_str().is_empty() && chars.all(|c| c.is_ascii_digit())
+}
+
+/// Operand of the given kind holding the value of an immediate expression, a float operand gets the value converted to a float.
+/// An integer immediate must fit in an `i32`, except a lone hexadecimal or binary literal which gives the 32 bits, so `#0xFFFFFFFF` is -1
+fn immediate_operand(kind: OperandKind, value: i64, expression: &Expression) -> Result<Operand, CompileError> {
+    match kind {
+        OperandKind::Float64 => Ok(Operand::Float(value as f64)),
+        _ if expression.is_bit_pattern() && (0..=u32::MAX as i64).contains(&value) => Ok(Operand::Immediate(value as u32 as i32)),
+        _ => i32::try_from(value).map(Operand::Immediate).map_err(|_| CompileError::ImmediateOverflow {
+            value: expression.text().to_string(),
+            span: expression.span(),
+        }),
+    }
+}
+
+/// Parses a decimal, hexadecimal or binary number literal, hexadecimal and binary literals are never negative unless prefixed with `-`.
+/// The value is only limited to an `i64` here, `immediate_operand` checks what an instruction can hold
+fn parse_number(literal: &str) -> Option<i64> {
+    let (negative, digits) = match literal.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, literal),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i128::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        i128::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse::<i128>().ok()?
+    };
+    i64::try_from(if negative { -value } else { value }).ok()
+}
+
+/// Error for a rule the grammar should never produce at this place
+fn unexpected(rule: Rule, pair: &Pair<Rule>) -> CompileError {
+    CompileError::Parse {
+        message: format!("unexpected {:?}", rule),
+        span: Span::of(pair),
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use pest::iterators::Pair;
+use reg_byte::Operand;
+
+use crate::expression::Expression;
+use crate::{CompileError, Rule, Span};
+
+/// Macro defined with `.macro name parameters ... .endm`, its body is assembled again at every invocation
+#[derive(Debug, Clone)]
+pub(crate) struct Macro<'i> {
+    pub(crate) parameters: Vec<String>,
+    /// Labels defined by the body, renamed at every expansion
+    pub(crate) labels: Vec<String>,
+    /// `MACRO_BODY` pair holding the statements of the macro
+    pub(crate) body: Pair<'i, Rule>,
+}
+
+/// Operand given to a macro parameter, resolved where the macro is invoked
+#[derive(Debug, Clone)]
+pub(crate) enum Argument {
+    /// Register, alias or float literal
+    Operand(Operand),
+    /// Constant expression, evaluated where the parameter is used
+    Immediate(Expression),
+}
+
+/// Names only visible to the statements of a macro expansion. The file itself is assembled in an empty scope
+#[derive(Debug, Default)]
+pub(crate) struct Scope {
+    pub(crate) arguments: HashMap<String, Argument>,
+    /// Name unique to this expansion of every label defined by the macro
+    pub(crate) labels: HashMap<String, String>,
+    /// Aliases defined by the macro body
+    pub(crate) aliases: HashMap<String, Operand>,
+    /// Macros being expanded, outermost first, with the position of their invocation
+    pub(crate) expansions: Vec<(String, Span)>,
+}
+
+impl<'i> Macro<'i> {
+    /// Builds the macro of a `MACRO` pair and returns it with its name.
+    /// Every parameter used by the body must be declared, and labels can't be defined twice in the body
+    pub(crate) fn parse(pair: &Pair<'i, Rule>) -> Result<(Pair<'i, Rule>, Self), CompileError> {
+        let mut inner = pair.clone().into_inner();
+        let name = inner.next().expect("MACRO always starts with an IDENT");
+        let mut parameters: Vec<String> = Vec::new();
+        for parameter in inner.next().expect("MACRO always has MACRO_PARAMETERS").into_inner() {
+            if parameters.iter().any(|declared| declared == parameter.as_str()) {
+                return Err(CompileError::DuplicateParameter {
+                    parameter: parameter.as_str().to_string(),
+                    span: Span::of(&parameter),
+                });
+            }
+            parameters.push(parameter.as_str().to_string());
+        }
+        let body = inner.next().expect("MACRO always ends with a MACRO_BODY");
+        let mut labels: Vec<String> = Vec::new();
+        for token in body.clone().into_inner().flatten() {
+            match token.as_rule() {
+                Rule::MACRO_PARAM if !parameters.iter().any(|parameter| *parameter == token.as_str()[1..]) => {
+                    return Err(CompileError::UndefinedParameter {
+                        parameter: token.as_str().to_string(),
+                        span: Span::of(&token),
+                    });
+                },
+                Rule::LABEL => {
+                    let label = token.as_str().trim_end_matches(':');
+                    if labels.iter().any(|defined| defined == label) {
+                        return Err(CompileError::DuplicateLabel {
+                            label: label.to_string(),
+                            span: Span::of(&token),
+                        });
+                    }
+                    labels.push(label.to_string());
+                },
+                _ => {},
+            }
+        }
+        Ok((name, Self { parameters, labels, body }))
+    }
+}
+
+impl Argument {
+    /// Source of the argument, used in error messages
+    pub(crate) fn text(&self) -> String {
+        match self {
+            Argument::Operand(operand) => operand.to_string(),
+            Argument::Immediate(expression) => expression.text().to_string(),
+        }
+    }
+}
+
+impl Scope {
+    /// Returns true while a macro body is assembled
+    pub(crate) fn in_macro(&self) -> bool {
+        !self.expansions.is_empty()
+    }
+    /// Argument of a `MACRO_PARAM` pair (`\name`)
+    pub(crate) fn argument(&self, pair: &Pair<Rule>) -> Result<&Argument, CompileError> {
+        self.arguments.get(&pair.as_str()[1..]).ok_or_else(|| CompileError::UndefinedParameter {
+            parameter: pair.as_str().to_string(),
+            span: Span::of(pair),
+        })
+    }
+    /// Builds the expression of an `IMMEDIATE` pair, with the labels of the macro renamed for this expansion
+    pub(crate) fn expression(&self, pair: &Pair<Rule>) -> Result<Expression, CompileError> {
+        let mut expression = Expression::parse(pair)?;
+        expression.rename(&self.labels);
+        Ok(expression)
+    }
+}
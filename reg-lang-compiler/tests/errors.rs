@@ -0,0 +1,61 @@
+use reg_lang_compiler::{CompileError, RegCompiler, Span};
+
+fn error(source: &str) -> CompileError {
+    match RegCompiler::compile(source) {
+        Ok(compiled) => panic!("{} assembled to {:?}", source, compiled.program),
+        Err(error) => error,
+    }
+}
+
+#[test]
+fn parse_error_points_at_the_unexpected_token() {
+    assert_eq!(error("STORE $0 #1\nSTORE $1 $2"), CompileError::Parse {
+        message: "expected a macro parameter or an immediate".to_string(),
+        span: Span { line: 2, column: 10 },
+    });
+}
+
+#[test]
+fn register_out_of_range() {
+    assert_eq!(error("STORE $32 #1"), CompileError::RegisterOutOfRange {
+        register: "$32".to_string(),
+        span: Span { line: 1, column: 7 },
+    });
+    assert_eq!(error("INC $0\nFADD $f0 $f40 $f0"), CompileError::RegisterOutOfRange {
+        register: "$f40".to_string(),
+        span: Span { line: 2, column: 10 },
+    });
+    assert_eq!(error("STORE $99999999999999999999 #1"), CompileError::RegisterOutOfRange {
+        register: "$99999999999999999999".to_string(),
+        span: Span { line: 1, column: 7 },
+    });
+}
+
+#[test]
+fn immediate_overflow() {
+    let error = error("HLT\n  STORE $0 #2147483648");
+    assert_eq!(error, CompileError::ImmediateOverflow {
+        value: "#2147483648".to_string(),
+        span: Span { line: 2, column: 12 },
+    });
+    assert_eq!(error.to_string(), "2:12: immediate #2147483648 doesn't fit in 32 bits");
+}
+
+#[test]
+fn float_overflow() {
+    assert_eq!(error("FSTORE $f0 #1e999"), CompileError::FloatOverflow {
+        value: "#1e999".to_string(),
+        span: Span { line: 1, column: 12 },
+    });
+}
+
+#[test]
+fn unknown_mnemonic() {
+    let error = error("INC $0\nJUMP $0");
+    assert_eq!(error, CompileError::UnknownMnemonic {
+        mnemonic: "JUMP".to_string(),
+        span: Span { line: 2, column: 1 },
+    });
+    assert_eq!(error.to_string(), "2:1: unknown mnemonic JUMP");
+    assert_eq!(error.span(), Span { line: 2, column: 1 });
+}
@@ -0,0 +1,53 @@
+use reg_byte::{Instruction, Operand};
+use reg_lang_compiler::{CompileError, RegCompiler};
+
+/// Immediate stored by the single instruction of `source`
+fn immediate(source: &str) -> i32 {
+    let program = RegCompiler::compile(source).unwrap_or_else(|e| panic!("{}", e)).program;
+    match Instruction::decode(&program, 0).unwrap().operands().last() {
+        Some(Operand::Immediate(value)) => *value,
+        operand => panic!("{:?} is not an immediate", operand),
+    }
+}
+
+#[test]
+fn hexadecimal_literals_are_positive_in_expressions() {
+    assert_eq!(immediate("STORE $0 #0x80000000 / 2"), 0x4000_0000);
+    assert_eq!(immediate(".equ M 0xFFFFFFFF\nSTORE $0 #M / 2"), 0x7FFF_FFFF);
+    assert_eq!(immediate("STORE $0 #0b11111111111111111111111111111111 - 0xFFFFFFFE"), 1);
+}
+
+#[test]
+fn constants_can_exceed_32_bits() {
+    assert_eq!(immediate(".equ A 3000000000\nSTORE $0 #A / 2"), 1_500_000_000);
+    assert_eq!(immediate(".equ BIG 0x100000000\nSTORE $0 #BIG - 0xFFFFFFFF"), 1);
+}
+
+#[test]
+fn hexadecimal_and_binary_immediates_give_the_32_bits() {
+    assert_eq!(immediate("STORE $0 #0xFFFFFFFF"), -1);
+    assert_eq!(immediate("STORE $0 #0x80000000"), i32::MIN);
+    assert_eq!(immediate("STORE $0 #0b10000000000000000000000000000001"), i32::MIN + 1);
+    assert_eq!(immediate("STORE $0 #-2147483648"), i32::MIN);
+    assert_eq!(immediate("STORE $0 #2147483647"), i32::MAX);
+}
+
+#[test]
+fn immediates_must_fit_in_32_bits() {
+    let sources = [
+        "STORE $0 #3000000000",
+        "STORE $0 #4294967295",
+        "STORE $0 #0x80000000 + 0",
+        ".equ M 0xFFFFFFFF\nSTORE $0 #M",
+        "STORE $0 #0x100000000",
+        "STORE $0 #-0x80000001",
+        "STORE $0 #-2147483649",
+        "STORE $0 #99999999999999999999",
+    ];
+    for source in sources {
+        match RegCompiler::compile(source) {
+            Err(CompileError::ImmediateOverflow { .. }) => {},
+            result => panic!("{}: {:?}", source, result.map(|compiled| compiled.program)),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::fs;
+use std::path::PathBuf;
+
+use reg_lang_compiler::{CompileError, RegCompiler};
+
+/// Empty directory of the temporary directory, unique to `test`
+fn directory(test: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("reg-lang-include-{}-{}", std::process::id(), test));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+#[test]
+fn aliases_of_an_included_file_stay_in_it() {
+    let directory = directory("alias-leak");
+    fs::write(directory.join("util.rb"), ".alias counter $3\nINC $counter").unwrap();
+    let compiled = RegCompiler::compile_in(".include \"util.rb\"\n.alias counter $4\nINC $counter", &directory)
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(compiled.program, RegCompiler::compile("INC $3\nINC $4").unwrap().program);
+}
+
+#[test]
+fn aliases_of_the_includer_are_not_visible_in_the_included_file() {
+    let directory = directory("alias-hidden");
+    fs::write(directory.join("util.rb"), "INC $counter").unwrap();
+    match RegCompiler::compile_in(".alias counter $3\n.include \"util.rb\"", &directory) {
+        Err(CompileError::InInclude { error, .. }) => assert!(matches!(*error, CompileError::UndefinedAlias { .. }), "{}", error),
+        result => panic!("{:?}", result.map(|compiled| compiled.program)),
+    }
+}
+
+#[test]
+fn aliases_of_the_includer_are_restored_after_the_include() {
+    let directory = directory("alias-restored");
+    fs::write(directory.join("util.rb"), ".alias counter $5").unwrap();
+    let compiled = RegCompiler::compile_in(".alias counter $3\n.include \"util.rb\"\nINC $counter", &directory)
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(compiled.program, RegCompiler::compile("INC $3").unwrap().program);
+}
@@ -0,0 +1,30 @@
+use reg_lang_compiler::{CompileError, RegCompiler, Span};
+
+#[test]
+fn labels_resolve_forward_and_backward() {
+    let compiled = RegCompiler::compile("start: STORE $0 #end\nmiddle:\nSTORE $1 #start\nend:").unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(compiled.labels["start"], 0);
+    assert_eq!(compiled.labels["middle"], 6);
+    assert_eq!(compiled.labels["end"], 12);
+    assert_eq!(compiled.program, RegCompiler::compile("STORE $0 #12\nSTORE $1 #0").unwrap().program);
+}
+
+#[test]
+fn duplicate_label() {
+    let error = RegCompiler::compile("loop:\nINC $0\n  loop: HLT").unwrap_err();
+    assert_eq!(error, CompileError::DuplicateLabel {
+        label: "loop".to_string(),
+        span: Span { line: 3, column: 3 },
+    });
+    assert_eq!(error.to_string(), "3:3: label loop is already defined");
+}
+
+#[test]
+fn undefined_label() {
+    let error = RegCompiler::compile("STORE $0 #start\nstart:\nSTORE $1 #nowhere + 1").unwrap_err();
+    assert_eq!(error, CompileError::UndefinedLabel {
+        label: "nowhere".to_string(),
+        span: Span { line: 3, column: 11 },
+    });
+    assert_eq!(error.to_string(), "3:11: undefined label or constant nowhere");
+}
@@ -0,0 +1,64 @@
+use reg_lang_compiler::{CompileError, RegCompiler, Span};
+
+fn program(source: &str) -> Vec<u8> {
+    RegCompiler::compile(source).unwrap_or_else(|e| panic!("{}", e)).program
+}
+
+fn error(source: &str) -> CompileError {
+    match RegCompiler::compile(source) {
+        Ok(compiled) => panic!("{} assembled to {:?}", source, compiled.program),
+        Err(error) => error,
+    }
+}
+
+#[test]
+fn every_expansion_has_its_own_labels() {
+    let source = ".macro here reg\nhere:\nSTORE \\reg #here\n.endm\nhere $1\nhere $2";
+    assert_eq!(program(source), program("a:\nSTORE $1 #a\nb:\nSTORE $2 #b"));
+}
+
+#[test]
+fn labels_of_an_expansion_are_not_visible_outside() {
+    let source = ".macro here\nhere:\nINC $0\n.endm\nhere\nSTORE $0 #here";
+    assert_eq!(error(source), CompileError::UndefinedLabel {
+        label: "here".to_string(),
+        span: Span { line: 6, column: 11 },
+    });
+}
+
+#[test]
+fn errors_in_the_body_give_the_definition_and_the_invocation() {
+    let error = error(".macro bad\nSTORE $40 #1\n.endm\nINC $0\nbad");
+    assert_eq!(error, CompileError::InMacro {
+        name: "bad".to_string(),
+        invocation: Span { line: 5, column: 1 },
+        error: Box::new(CompileError::RegisterOutOfRange {
+            register: "$40".to_string(),
+            span: Span { line: 2, column: 7 },
+        }),
+    });
+    assert!(error.to_string().ends_with("(in the expansion of macro bad at 5:1)"), "{}", error);
+}
+
+#[test]
+fn aliases_of_a_macro_body_stay_in_its_expansion() {
+    let source = ".macro bump\n.alias counter $3\nINC $counter\n.endm\nbump\nbump\n.alias counter $4\nINC $counter";
+    assert_eq!(program(source), program("INC $3\nINC $3\nINC $4"));
+}
+
+#[test]
+fn invocations_are_checked() {
+    let source = ".macro pair a b\nADD \\a \\b \\a\n.endm\n";
+    assert!(matches!(
+        error(&format!("{}pair $1", source)),
+        CompileError::MacroArguments { expected: 2, found: 1, .. }
+    ));
+    assert!(matches!(
+        error(&format!("{}pair $1 #2", source)),
+        CompileError::InMacro { error, .. } if matches!(*error, CompileError::WrongArgument { .. })
+    ));
+    assert!(matches!(
+        error(".macro again\nagain\n.endm\nagain"),
+        CompileError::InMacro { error, .. } if matches!(*error, CompileError::MacroRecursion { .. })
+    ));
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use reg_byte::object::{self, LinkError, ObjectFile, Relocation, Symbol};
+use reg_lang_compiler::{CompileError, RegCompiler};
+
+fn object(source: &str) -> ObjectFile {
+    RegCompiler::compile_object(source, Path::new("")).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Immediate at `offset` in `code`
+fn immediate(code: &[u8], offset: usize) -> i32 {
+    i32::from_be_bytes(code[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn labels_are_relocated_by_the_address_of_their_module() {
+    let first = object("INC $0\nINC $0");
+    let second = object("INC $1\ntbl:\nSTORE $2 #tbl + 1");
+    let tbl = RegCompiler::compile("INC $1").unwrap().program.len();
+    let [Relocation::Local { offset }] = second.relocations[..] else {
+        panic!("{:?}", second.relocations);
+    };
+    assert_eq!(immediate(&second.code, offset as usize), tbl as i32 + 1);
+
+    let linked = object::link(&[first.clone(), second]).unwrap();
+    assert_eq!(linked.entry_point, 0);
+    assert_eq!(immediate(&linked.code, first.code.len() + offset as usize), (first.code.len() + tbl) as i32 + 1);
+}
+
+#[test]
+fn imported_symbols_get_the_address_of_their_export() {
+    let main = object(".extern square\nSTORE $0 #square + 2\nCALL $0");
+    let util = object(".global square\nINC $5\nsquare:\nMUL $1 $1 $1\nRET");
+    assert_eq!(main.imports, ["square"]);
+    let [Relocation::Import { offset, import: 0 }] = main.relocations[..] else {
+        panic!("{:?}", main.relocations);
+    };
+    assert_eq!(immediate(&main.code, offset as usize), 2);
+    let [Symbol { ref name, offset: square }] = util.exports[..] else {
+        panic!("{:?}", util.exports);
+    };
+    assert_eq!(name, "square");
+
+    let linked = object::link(&[main.clone(), util]).unwrap();
+    assert_eq!(immediate(&linked.code, offset as usize), (main.code.len() + square as usize) as i32 + 2);
+}
+
+#[test]
+fn only_a_label_plus_a_number_can_be_relocated() {
+    match RegCompiler::compile_object("x:\nSTORE $0 #x * 2", Path::new("")) {
+        Err(CompileError::NotRelocatable { expression, .. }) => assert_eq!(expression, "#x * 2"),
+        result => panic!("{:?}", result),
+    }
+    assert!(RegCompiler::compile("x:\nSTORE $0 #x * 2").is_ok());
+}
+
+#[test]
+fn symbols_must_be_exported_once() {
+    let util = object(".global f\nf:\nRET");
+    let main = object(".extern f\nSTORE $0 #f\nCALL $0");
+    assert_eq!(object::link(&[main.clone(), util.clone(), util]), Err(LinkError::DuplicateSymbol {
+        symbol: "f".to_string(),
+        module: 2,
+    }));
+    assert_eq!(object::link(&[object("INC $0"), main]), Err(LinkError::UndefinedSymbol {
+        symbol: "f".to_string(),
+        module: 1,
+    }));
+}
@@ -0,0 +1,40 @@
+use reg_byte::{Instruction, OpCode, Operand, OperandKind};
+use reg_lang_compiler::RegCompiler;
+
+/// Every opcode the VM understands, with sample operands following its layout
+fn every_instruction() -> Vec<Instruction> {
+    (0..=u8::MAX)
+        .map(OpCode::from)
+        .filter(|opcode| *opcode != OpCode::ILG)
+        .map(|opcode| {
+            let operands = opcode.operands().iter().enumerate().map(|(i, kind)| match kind {
+                OperandKind::Register => Operand::Register(i as u8 + 1),
+                OperandKind::Immediate32 => Operand::Immediate(-0x12345678),
+                OperandKind::FloatRegister => Operand::FloatRegister(i as u8 + 1),
+                OperandKind::Float64 => Operand::Float(-1.5e-7),
+            }).collect::<Vec<_>>();
+            Instruction::new(opcode, &operands)
+        })
+        .collect()
+}
+
+#[test]
+fn every_opcode_decodes_what_it_encodes() {
+    for instruction in every_instruction() {
+        let mut program = vec![];
+        instruction.encode(&mut program);
+        assert_eq!(program.len(), instruction.width(), "{}", instruction);
+        assert_eq!(Instruction::decode(&program, 0), Ok(instruction));
+    }
+}
+
+#[test]
+fn every_opcode_assembles_to_its_encoding() {
+    for instruction in every_instruction() {
+        let mut expected = vec![];
+        instruction.encode(&mut expected);
+        let compiled = RegCompiler::compile(&instruction.to_string()).unwrap_or_else(|e| panic!("{}: {}", instruction, e));
+        assert_eq!(compiled.program, expected, "{}", instruction);
+        assert_eq!(reg_byte::to_source(&compiled.program).unwrap().trim_end(), instruction.to_string());
+    }
+}
@@ -0,0 +1,55 @@
+use reg_lang_compiler::{CompileError, RegCompiler, Span};
+
+fn program(source: &str) -> Vec<u8> {
+    RegCompiler::compile(source).unwrap_or_else(|e| panic!("{}", e)).program
+}
+
+/// Message of the parse error of `source`, with its position
+fn parse_error(source: &str) -> String {
+    match RegCompiler::compile(source) {
+        Err(error @ CompileError::Parse { .. }) => error.to_string(),
+        result => panic!("{}: {:?}", source, result.map(|compiled| compiled.program)),
+    }
+}
+
+#[test]
+fn comments_and_blank_lines_are_ignored() {
+    let source = "; counts to 2\n\n  STORE $0 #1 ; first\n\t// then\nINC $0 // second\n\n";
+    assert_eq!(program(source), program("STORE $0 #1\nINC $0"));
+    assert_eq!(program("; only a comment"), Vec::<u8>::new());
+    assert_eq!(program("STORE $0 #2 ;# not an immediate"), program("STORE $0 #2"));
+}
+
+#[test]
+fn mnemonics_are_case_insensitive() {
+    assert_eq!(program("store $0 #1\nStOrE $1 #2\nadd $0 $1 $2\nhlt"), program("STORE $0 #1\nSTORE $1 #2\nADD $0 $1 $2\nHLT"));
+    assert_eq!(program("fstore $F1 #1.5\nPush $0\npop $1"), program("FSTORE $f1 #1.5\nPUSH $0\nPOP $1"));
+}
+
+#[test]
+fn instructions_need_their_own_line() {
+    assert_eq!(parse_error("INC $0 INC $1"), "1:8: expected end of line");
+    assert_eq!(parse_error("INC $0\nADD $0 $1 $2 $3"), "2:14: expected end of line");
+}
+
+#[test]
+fn diagnostics_give_the_line_and_column() {
+    let error = RegCompiler::compile("; header\n\nSTORE $0 #1\n    ADD $0 $1 $40").unwrap_err();
+    assert_eq!(error, CompileError::RegisterOutOfRange {
+        register: "$40".to_string(),
+        span: Span { line: 4, column: 15 },
+    });
+    assert_eq!(error.to_string(), "4:15: register $40 out of range (expected $0 to $31)");
+}
+
+#[test]
+fn parse_errors_name_what_was_expected() {
+    assert_eq!(parse_error("STORE $0 #"), "1:11: expected an immediate");
+    assert_eq!(parse_error("STORE $0 #1 +"), "1:14: expected an immediate");
+    assert_eq!(parse_error("STORE $0 #1 2"), "1:13: expected end of line or an operator");
+    assert_eq!(parse_error("ADD $0 $1"), "1:10: expected a register, an alias, or a macro parameter");
+    assert_eq!(parse_error("FADD $0 $f1 $f2"), "1:6: expected a float register, an alias, or a macro parameter");
+    assert_eq!(parse_error(".equ"), "1:5: expected a name");
+    assert_eq!(parse_error(".include"), "1:9: expected a quoted path");
+    assert_eq!(parse_error("@"), "1:1: expected a label, a directive, or an instruction");
+}
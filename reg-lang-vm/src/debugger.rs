@@ -0,0 +1,121 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::{ExitStatus, RegLangVM, VmError};
+
+/// Runs a VM instruction per instruction or until a breakpoint
+#[derive(Debug)]
+pub struct Debugger {
+    pub vm: RegLangVM,
+    /// Byte offsets where `resume` stops
+    breakpoints: BTreeSet<usize>,
+    /// Byte offset of every label of the program, empty if the program has no symbols
+    labels: HashMap<String, usize>,
+    /// Byte offset where the last `step` or `resume` left the program, `None` before the first one
+    stopped_at: Option<usize>,
+    /// Byte offset following the `HLT` that halted the program, it doesn't run again until the program counter moves
+    halted_at: Option<usize>,
+}
+impl Debugger {
+    pub fn new(vm: RegLangVM, labels: HashMap<String, usize>) -> Self {
+        Self {
+            vm,
+            breakpoints: BTreeSet::new(),
+            labels,
+            stopped_at: None,
+            halted_at: None,
+        }
+    }
+    /// Resolves a location given as a label name or a byte offset
+    pub fn resolve(&self, location: &str) -> Option<usize> {
+        self.labels.get(location).copied().or_else(|| location.parse().ok())
+    }
+    /// Returns the name of a label defined at `offset`, if any
+    pub fn label_at(&self, offset: usize) -> Option<&str> {
+        self.labels.iter().find(|(_, address)| **address == offset).map(|(label, _)| label.as_str())
+    }
+    /// Adds a breakpoint at a label or a byte offset, returns the byte offset.
+    /// The offset must be the start of an instruction of the program
+    pub fn add_breakpoint(&mut self, location: &str) -> Result<usize, BreakpointError> {
+        let offset = self.resolve(location).ok_or_else(|| BreakpointError::UnknownLocation(location.to_string()))?;
+        if offset >= self.vm.program.len() {
+            return Err(BreakpointError::OutsideProgram(offset));
+        }
+        if !reg_byte::disassemble(&self.vm.program).iter().any(|line| line.offset == offset) {
+            return Err(BreakpointError::InsideInstruction(offset));
+        }
+        self.breakpoints.insert(offset);
+        Ok(offset)
+    }
+    /// Removes a breakpoint at a label or a byte offset, returns the byte offset if there was one
+    pub fn remove_breakpoint(&mut self, location: &str) -> Option<usize> {
+        let offset = self.resolve(location)?;
+        self.breakpoints.remove(&offset).then_some(offset)
+    }
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+    /// Executes the next instruction, ignoring breakpoints.
+    /// A halted program stays `Halted` without executing anything until the program counter is moved
+    pub fn step(&mut self) -> Result<ExitStatus, VmError> {
+        if self.is_halted() {
+            return Ok(ExitStatus::Halted);
+        }
+        let status = self.vm.run_once();
+        self.stop(&status);
+        status
+    }
+    /// Executes instructions until the program stops or the next breakpoint, which can be on the first instruction.
+    /// Only the breakpoint the program is stopped on is skipped, so a stopped program can be resumed.
+    /// A halted program stays `Halted` without executing anything until the program counter is moved
+    pub fn resume(&mut self) -> Result<ExitStatus, VmError> {
+        if self.is_halted() {
+            return Ok(ExitStatus::Halted);
+        }
+        let mut skipped = self.stopped_at.take();
+        let status = loop {
+            let program_counter = self.vm.program_counter();
+            if self.breakpoints.contains(&program_counter) && skipped != Some(program_counter) {
+                break Ok(ExitStatus::Breakpoint(program_counter));
+            }
+            skipped = None;
+            match self.vm.run_once() {
+                Ok(ExitStatus::Running) => {},
+                status => break status,
+            }
+        };
+        self.stop(&status);
+        status
+    }
+    /// Whether a `HLT` stopped the program and the program counter is still after it
+    pub fn is_halted(&self) -> bool {
+        self.halted_at == Some(self.vm.program_counter())
+    }
+    /// Records where the program stopped
+    fn stop(&mut self, status: &Result<ExitStatus, VmError>) {
+        let program_counter = self.vm.program_counter();
+        self.stopped_at = Some(program_counter);
+        self.halted_at = (*status == Ok(ExitStatus::Halted)).then_some(program_counter);
+    }
+}
+
+/// Error returned when a breakpoint can't be added
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakpointError {
+    /// The location is neither a label nor a byte offset
+    UnknownLocation(String),
+    /// The byte offset is at or after the end of the program
+    OutsideProgram(usize),
+    /// The byte offset is in the middle of an instruction
+    InsideInstruction(usize),
+}
+impl fmt::Display for BreakpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BreakpointError::UnknownLocation(location) => write!(f, "unknown label or offset {}", location),
+            BreakpointError::OutsideProgram(offset) => write!(f, "byte {} is outside of the program", offset),
+            BreakpointError::InsideInstruction(offset) => write!(f, "byte {} is in the middle of an instruction", offset),
+        }
+    }
+}
+impl std::error::Error for BreakpointError {}
@@ -0,0 +1,85 @@
+use std::fmt;
+use std::io;
+
+use reg_byte::OpCode;
+
+/// Reason the VM stopped executing instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// An instruction was executed and the program can go on
+    Running,
+    /// A `HLT` instruction was executed
+    Halted,
+    /// The program counter reached the end of the program
+    EndOfProgram,
+    /// `run_with_limit` executed its maximum number of instructions, running again resumes the program
+    OutOfFuel,
+    /// `run_with_timeout` ran for its whole duration, running again resumes the program
+    OutOfTime,
+    /// The `Debugger` reached a breakpoint, before executing the instruction at that byte
+    Breakpoint(usize),
+}
+
+/// Kind of fault raised by a bad program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmErrorKind {
+    /// The byte isn't a known opcode
+    IllegalOpcode(u8),
+    /// The register operand isn't one of the VM registers
+    InvalidRegister(u8),
+    /// The float register operand isn't one of the VM float registers
+    InvalidFloatRegister(u8),
+    /// The program ends in the middle of an instruction
+    UnexpectedEndOfProgram,
+    /// `DIV`, `DIVU` or `MOD` with a divisor of 0
+    DivisionByZero,
+    /// A jump targets a byte outside of the program
+    JumpOutOfBounds(i64),
+    /// `CALL` or `PUSH` on a full stack
+    StackOverflow,
+    /// `RET` or `POP` on an empty stack
+    StackUnderflow,
+    /// A load or a store reaches outside of the heap
+    HeapOutOfBounds(i64),
+    /// `ALOC` with a negative size or past the maximum heap size
+    AllocationFailed(i64),
+    /// The input or the output of the VM can't be used
+    Io(io::ErrorKind),
+    /// `READ` found something else than an integer
+    InvalidInput,
+}
+impl fmt::Display for VmErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmErrorKind::IllegalOpcode(byte) => write!(f, "illegal opcode 0x{:02X}", byte),
+            VmErrorKind::InvalidRegister(register) => write!(f, "invalid register ${}", register),
+            VmErrorKind::InvalidFloatRegister(register) => write!(f, "invalid float register $f{}", register),
+            VmErrorKind::UnexpectedEndOfProgram => write!(f, "unexpected end of program"),
+            VmErrorKind::DivisionByZero => write!(f, "division by zero"),
+            VmErrorKind::JumpOutOfBounds(target) => write!(f, "jump to byte {} is out of the program", target),
+            VmErrorKind::StackOverflow => write!(f, "stack overflow"),
+            VmErrorKind::StackUnderflow => write!(f, "stack underflow"),
+            VmErrorKind::HeapOutOfBounds(address) => write!(f, "heap access at address {} is out of bounds", address),
+            VmErrorKind::AllocationFailed(size) => write!(f, "can't allocate {} bytes on the heap", size),
+            VmErrorKind::Io(kind) => write!(f, "I/O error: {}", kind),
+            VmErrorKind::InvalidInput => write!(f, "input isn't an integer"),
+        }
+    }
+}
+
+/// Fault raised while executing an instruction, the VM is left on the faulting instruction which had no effect.
+/// A failing `Tracer` is the exception, see its documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VmError {
+    /// Byte offset of the faulting instruction, always the program counter of the VM after the fault
+    pub program_counter: usize,
+    /// Opcode of the faulting instruction, `OpCode::ILG` if the byte isn't an opcode
+    pub opcode: OpCode,
+    pub kind: VmErrorKind,
+}
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "fault at byte {} ({}): {}", self.program_counter, self.opcode.mnemonic(), self.kind)
+    }
+}
+impl std::error::Error for VmError {}
@@ -1,161 +1,806 @@
-use reg_byte::OpCode;
+mod debugger;
+mod error;
+mod trace;
+
+use std::any::Any;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use reg_byte::rbg::RbgFile;
+use reg_byte::{DecodeError, Instruction, OpCode, Operand, REGISTER_COUNT};
+
+pub use debugger::{BreakpointError, Debugger};
+pub use error::{ExitStatus, VmError, VmErrorKind};
+pub use trace::{JsonTracer, TextTracer, TraceEvent, Tracer};
+
+/// Maximum number of nested `CALL`
+pub const CALL_STACK_SIZE: usize = 1024;
+/// Maximum number of values on the stack used by `PUSH` and `POP`
+pub const STACK_SIZE: usize = 1024;
+/// Maximum number of bytes the heap can grow to
+pub const MAX_HEAP_SIZE: usize = 16 * 1024 * 1024;
+/// Number of instructions executed by `run_with_timeout` between two checks of the clock
+const INSTRUCTIONS_PER_CLOCK_CHECK: u32 = 1024;
+
+/// Writer of the VM output, `Any` gives back the writer given to `with_output`
+trait Output: Write + Any {}
+impl<T: Write + Any> Output for T {}
 
-#[derive(Debug)]
 pub struct RegLangVM {
     /// Array that simulates having hardware registers
-    registers: [i32; 32],
+    registers: [i64; REGISTER_COUNT],
+    /// Float registers, used by the float opcodes
+    float_registers: [f64; REGISTER_COUNT],
     /// Program counter that tracks which byte is being executed
     program_counter: usize,
     /// The bytecode of the program being run
     pub program: Vec<u8>,
     /// Contains the remainder of modulo division ops
-    remainder: u32,
+    remainder: u64,
     /// Contains the result of the last comparison operation
     equal_flag: bool,
+    /// Set by the last arithmetic operation if its unsigned result wrapped around
+    carry_flag: bool,
+    /// Set by the last arithmetic operation if its signed result wrapped around
+    overflow_flag: bool,
+    /// Return addresses of the `CALL` being executed
+    call_stack: Vec<usize>,
+    /// Values saved with `PUSH`
+    stack: Vec<i64>,
+    /// Byte addressable memory, grown with `ALOC`
+    heap: Vec<u8>,
+    /// Where `PRINT` and `PRINTLN` write, stdout by default
+    output: Box<dyn Output>,
+    /// Where `READ` and `READC` read, stdin by default
+    input: Box<dyn Read>,
+    /// Bytes given back to the input, the next read returns the last one first.
+    /// `READ` gives back the byte following a number, and everything it read when it faults
+    peeked_input: Vec<u8>,
+    /// Receives every executed instruction when set
+    tracer: Option<Box<dyn Tracer>>,
+    /// Registers written by the current instruction, only recorded when there is a tracer
+    traced_writes: Vec<(usize, i64)>,
+    /// Float registers written by the current instruction, only recorded when there is a tracer
+    traced_float_writes: Vec<(usize, f64)>,
+}
+impl fmt::Debug for RegLangVM {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RegLangVM")
+            .field("registers", &self.registers)
+            .field("float_registers", &self.float_registers)
+            .field("program_counter", &self.program_counter)
+            .field("program", &self.program)
+            .field("remainder", &self.remainder)
+            .field("equal_flag", &self.equal_flag)
+            .field("carry_flag", &self.carry_flag)
+            .field("overflow_flag", &self.overflow_flag)
+            .field("call_stack", &self.call_stack)
+            .field("stack", &self.stack)
+            .field("heap", &self.heap)
+            .finish_non_exhaustive()
+    }
 }
 impl RegLangVM {
     pub fn new(program: Vec<u8>) -> Self {
         Self {
-            registers: [0; 32],
+            registers: [0; REGISTER_COUNT],
+            float_registers: [0.0; REGISTER_COUNT],
             program_counter: 0,
             program,
             remainder: 0,
             equal_flag: false,
+            carry_flag: false,
+            overflow_flag: false,
+            call_stack: Vec::new(),
+            stack: Vec::new(),
+            heap: Vec::new(),
+            output: Box::new(io::stdout()),
+            input: Box::new(io::stdin()),
+            peeked_input: Vec::new(),
+            tracer: None,
+            traced_writes: Vec::new(),
+            traced_float_writes: Vec::new(),
         }
     }
+    /// Sends the output of `PRINT` and `PRINTLN` to `output` instead of stdout
+    pub fn with_output(mut self, output: impl Write + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+    /// Returns the writer given to `with_output` if it is a `W`, so that a `Vec<u8>` gives back what the program printed
+    pub fn output<W: Write + 'static>(&self) -> Option<&W> {
+        let output: &dyn Any = &*self.output;
+        output.downcast_ref()
+    }
+    /// Makes `READ` and `READC` read from `input` instead of stdin
+    pub fn with_input(mut self, input: Box<dyn Read>) -> Self {
+        self.input = input;
+        self.peeked_input.clear();
+        self
+    }
+    /// Sends every executed instruction to `tracer`
+    pub fn with_tracer(mut self, tracer: Box<dyn Tracer>) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+    /// Starts or stops tracing the executed instructions
+    pub fn set_tracer(&mut self, tracer: Option<Box<dyn Tracer>>) {
+        self.tracer = tracer;
+    }
+    /// Starts with a zeroed heap of `size` bytes, capped to `MAX_HEAP_SIZE`
+    pub fn with_heap_size(mut self, size: usize) -> Self {
+        self.heap.resize(size.min(MAX_HEAP_SIZE), 0);
+        self
+    }
+    /// Loads the code of a `.rbg` file, starting at its entry point, with its data at the start of the heap
+    pub fn from_rbg(file: RbgFile) -> Self {
+        let mut vm = Self::new(file.code);
+        vm.program_counter = file.entry_point as usize;
+        vm.heap = file.data;
+        vm
+    }
+    /// Replaces the program and starts it from its first byte.
+    /// Registers, flags, the stack and the heap are kept, so a program can use what the previous one left
+    pub fn load(&mut self, program: Vec<u8>) {
+        self.program = program;
+        self.program_counter = 0;
+        self.call_stack.clear();
+    }
     /// Loops as long as instructions can be executed.
-    pub fn run(&mut self) {
-        let mut is_done = false;
-        while !is_done {
-            is_done = self.execute_instruction();
+    pub fn run(&mut self) -> Result<ExitStatus, VmError> {
+        loop {
+            match self.execute_instruction()? {
+                ExitStatus::Running => {},
+                status => return Ok(status),
+            }
+        }
+    }
+    /// Executes at most `max_instructions` instructions, stopping with `ExitStatus::OutOfFuel` if the program isn't done
+    pub fn run_with_limit(&mut self, max_instructions: u64) -> Result<ExitStatus, VmError> {
+        for _ in 0..max_instructions {
+            match self.execute_instruction()? {
+                ExitStatus::Running => {},
+                status => return Ok(status),
+            }
+        }
+        if self.program_counter >= self.program.len() {
+            Ok(ExitStatus::EndOfProgram)
+        } else {
+            Ok(ExitStatus::OutOfFuel)
+        }
+    }
+    /// Executes instructions for at most `timeout`, stopping with `ExitStatus::OutOfTime` if the program isn't done.
+    /// The clock is only checked every 1024 instructions, so the program can run a little past `timeout`.
+    /// `READ` and `READC` block on the input without looking at the clock, a program waiting for input runs
+    /// until the input gives a byte or ends, however long that takes
+    pub fn run_with_timeout(&mut self, timeout: Duration) -> Result<ExitStatus, VmError> {
+        let start = Instant::now();
+        loop {
+            for _ in 0..INSTRUCTIONS_PER_CLOCK_CHECK {
+                match self.execute_instruction()? {
+                    ExitStatus::Running => {},
+                    status => return Ok(status),
+                }
+            }
+            if start.elapsed() >= timeout {
+                return Ok(ExitStatus::OutOfTime);
+            }
         }
     }
 
     /// Executes one instruction. Meant to allow for more controlled execution of the VM
-    pub fn run_once(&mut self) {
-        self.execute_instruction();
+    pub fn run_once(&mut self) -> Result<ExitStatus, VmError> {
+        self.execute_instruction()
+    }
+    /// Byte offset of the next instruction to execute
+    pub fn program_counter(&self) -> usize {
+        self.program_counter
+    }
+    /// Return addresses of the `CALL` being executed, the innermost last
+    pub fn call_stack(&self) -> &[usize] {
+        &self.call_stack
+    }
+    /// Moves the execution to another byte of the program
+    pub fn set_program_counter(&mut self, program_counter: usize) {
+        self.program_counter = program_counter;
+    }
+    pub fn registers(&self) -> &[i64; REGISTER_COUNT] {
+        &self.registers
+    }
+    /// Changes the value of a register, `index` must be lower than `REGISTER_COUNT`
+    pub fn set_register(&mut self, index: usize, value: i64) {
+        self.registers[index] = value;
+    }
+    pub fn float_registers(&self) -> &[f64; REGISTER_COUNT] {
+        &self.float_registers
+    }
+    /// Changes the value of a float register, `index` must be lower than `REGISTER_COUNT`
+    pub fn set_float_register(&mut self, index: usize, value: f64) {
+        self.float_registers[index] = value;
+    }
+    pub fn remainder(&self) -> u64 {
+        self.remainder
+    }
+    pub fn set_remainder(&mut self, remainder: u64) {
+        self.remainder = remainder;
+    }
+    pub fn equal_flag(&self) -> bool {
+        self.equal_flag
+    }
+    pub fn set_equal_flag(&mut self, equal_flag: bool) {
+        self.equal_flag = equal_flag;
     }
-    /// 
-    fn execute_instruction(&mut self) -> bool {
+    pub fn carry_flag(&self) -> bool {
+        self.carry_flag
+    }
+    pub fn set_carry_flag(&mut self, carry_flag: bool) {
+        self.carry_flag = carry_flag;
+    }
+    pub fn overflow_flag(&self) -> bool {
+        self.overflow_flag
+    }
+    pub fn set_overflow_flag(&mut self, overflow_flag: bool) {
+        self.overflow_flag = overflow_flag;
+    }
+    /// Decodes the next instruction to execute without executing it, `None` at the end of the program
+    pub fn current_instruction(&self) -> Option<Result<Instruction, DecodeError>> {
         if self.program_counter >= self.program.len() {
-            return true;
+            return None;
         }
-        match self.decode_opcode() {
+        Some(Instruction::decode(&self.program, self.program_counter))
+    }
+    /// Executes the instruction at the program counter.
+    /// A faulting instruction has no effect and the program counter stays at its start,
+    /// only what an I/O error let `PRINT` write can't be taken back.
+    /// A failing tracer is reported once the instruction executed, with the program counter after it
+    fn execute_instruction(&mut self) -> Result<ExitStatus, VmError> {
+        if self.program_counter >= self.program.len() {
+            return Ok(ExitStatus::EndOfProgram);
+        }
+        let start = self.program_counter;
+        let instruction = self.decode_instruction().map_err(|kind| VmError {
+            program_counter: start,
+            opcode: OpCode::from(self.program[start]),
+            kind,
+        })?;
+        // Every register is checked before anything changes, the flags are set before the destination register is written
+        check_registers(&instruction).map_err(|kind| VmError { program_counter: start, opcode: instruction.opcode, kind })?;
+        self.program_counter += instruction.width();
+        let equal_flag = self.equal_flag;
+        let remainder = self.remainder;
+        let carry_flag = self.carry_flag;
+        let overflow_flag = self.overflow_flag;
+        self.traced_writes.clear();
+        self.traced_float_writes.clear();
+        let status = self.execute(&instruction).map_err(|kind| {
+            self.program_counter = start;
+            VmError { program_counter: start, opcode: instruction.opcode, kind }
+        })?;
+        if let Some(tracer) = &mut self.tracer {
+            let opcode = instruction.opcode;
+            let event = TraceEvent {
+                program_counter: start,
+                instruction,
+                register_writes: std::mem::take(&mut self.traced_writes),
+                float_register_writes: std::mem::take(&mut self.traced_float_writes),
+                equal_flag: (self.equal_flag != equal_flag).then_some(self.equal_flag),
+                remainder: (self.remainder != remainder).then_some(self.remainder),
+                carry_flag: (self.carry_flag != carry_flag).then_some(self.carry_flag),
+                overflow_flag: (self.overflow_flag != overflow_flag).then_some(self.overflow_flag),
+            };
+            tracer.trace(&event).map_err(|e| VmError {
+                program_counter: self.program_counter,
+                opcode,
+                kind: VmErrorKind::Io(e.kind()),
+            })?;
+        }
+        Ok(status)
+    }
+    fn execute(&mut self, instruction: &Instruction) -> Result<ExitStatus, VmErrorKind> {
+        match instruction.opcode {
             OpCode::STORE => {
-                let register = self.next_8_bits() as usize;
-                let number = self.next_16_bits() as u32;
-                self.registers[register] = number as i32;
+                self.write_register(instruction, 0, immediate(instruction, 1) as i64)?;
+            },
+            OpCode::MOV => {
+                let value = self.read_register(instruction, 0)?;
+                self.write_register(instruction, 1, value)?;
             },
             OpCode::HLT => {
-                return true;
+                return Ok(ExitStatus::Halted);
             },
             OpCode::ADD => {
-                let register1 = self.next_8_bits() as usize;
-                let register2 = self.next_8_bits() as usize;
-                self.registers[self.next_8_bits() as usize] = self.registers[register1] + self.registers[register2];
+                let register1 = self.read_register(instruction, 0)?;
+                let register2 = self.read_register(instruction, 1)?;
+                let (value, overflow) = register1.overflowing_add(register2);
+                self.carry_flag = (register1 as u64).overflowing_add(register2 as u64).1;
+                self.overflow_flag = overflow;
+                self.write_register(instruction, 2, value)?;
             },
             OpCode::MUL => {
-                let register1 = self.next_8_bits() as usize;
-                let register2 = self.next_8_bits() as usize;
-                self.registers[self.next_8_bits() as usize] = self.registers[register1] * self.registers[register2];
+                let register1 = self.read_register(instruction, 0)?;
+                let register2 = self.read_register(instruction, 1)?;
+                let (value, overflow) = register1.overflowing_mul(register2);
+                self.carry_flag = (register1 as u64).overflowing_mul(register2 as u64).1;
+                self.overflow_flag = overflow;
+                self.write_register(instruction, 2, value)?;
             },
             OpCode::SUB => {
-                let register1 = self.next_8_bits() as usize;
-                let register2 = self.next_8_bits() as usize;
-                self.registers[self.next_8_bits() as usize] = self.registers[register1] - self.registers[register2];
+                let register1 = self.read_register(instruction, 0)?;
+                let register2 = self.read_register(instruction, 1)?;
+                let (value, overflow) = register1.overflowing_sub(register2);
+                self.carry_flag = (register1 as u64).overflowing_sub(register2 as u64).1;
+                self.overflow_flag = overflow;
+                self.write_register(instruction, 2, value)?;
             },
             OpCode::DIV => {
-                let register1 = self.registers[self.next_8_bits() as usize];
-                let register2 = self.registers[self.next_8_bits() as usize];
-                self.registers[self.next_8_bits() as usize] = register1 / register2;
-                self.remainder = (register1 % register2) as u32;
+                let register1 = self.read_register(instruction, 0)?;
+                let register2 = self.read_register(instruction, 1)?;
+                if register2 == 0 {
+                    return Err(VmErrorKind::DivisionByZero);
+                }
+                // Only `i64::MIN / -1` overflows, it gives `i64::MIN` with a remainder of 0
+                let (value, overflow) = register1.overflowing_div(register2);
+                self.carry_flag = false;
+                self.overflow_flag = overflow;
+                self.write_register(instruction, 2, value)?;
+                self.remainder = register1.wrapping_rem(register2) as u64;
+            },
+            OpCode::DIVU => {
+                let register1 = self.read_register(instruction, 0)? as u64;
+                let register2 = self.read_register(instruction, 1)? as u64;
+                if register2 == 0 {
+                    return Err(VmErrorKind::DivisionByZero);
+                }
+                self.carry_flag = false;
+                self.overflow_flag = false;
+                self.write_register(instruction, 2, (register1 / register2) as i64)?;
+                self.remainder = register1 % register2;
+            },
+            OpCode::INC => {
+                let register = self.read_register(instruction, 0)?;
+                let (value, overflow) = register.overflowing_add(1);
+                self.carry_flag = register == -1;
+                self.overflow_flag = overflow;
+                self.write_register(instruction, 0, value)?;
+            },
+            OpCode::DEC => {
+                let register = self.read_register(instruction, 0)?;
+                let (value, overflow) = register.overflowing_sub(1);
+                self.carry_flag = register == 0;
+                self.overflow_flag = overflow;
+                self.write_register(instruction, 0, value)?;
+            },
+            OpCode::NEG => {
+                // Same as subtracting the register from 0
+                let register = self.read_register(instruction, 0)?;
+                let (value, overflow) = register.overflowing_neg();
+                self.carry_flag = register != 0;
+                self.overflow_flag = overflow;
+                self.write_register(instruction, 1, value)?;
+            },
+            OpCode::MOD => {
+                let register1 = self.read_register(instruction, 0)?;
+                let register2 = self.read_register(instruction, 1)?;
+                if register2 == 0 {
+                    return Err(VmErrorKind::DivisionByZero);
+                }
+                self.write_register(instruction, 2, register1.wrapping_rem(register2))?;
+            },
+            OpCode::GETREM => {
+                self.write_register(instruction, 0, self.remainder as i64)?;
+            },
+            OpCode::GETFLAG => {
+                let flags = self.equal_flag as i64 | (self.carry_flag as i64) << 1 | (self.overflow_flag as i64) << 2;
+                self.write_register(instruction, 0, flags)?;
+            },
+            OpCode::AND => {
+                let value = self.read_register(instruction, 0)? & self.read_register(instruction, 1)?;
+                self.write_register(instruction, 2, value)?;
+            },
+            OpCode::OR => {
+                let value = self.read_register(instruction, 0)? | self.read_register(instruction, 1)?;
+                self.write_register(instruction, 2, value)?;
+            },
+            OpCode::XOR => {
+                let value = self.read_register(instruction, 0)? ^ self.read_register(instruction, 1)?;
+                self.write_register(instruction, 2, value)?;
+            },
+            OpCode::NOT => {
+                let value = !self.read_register(instruction, 0)?;
+                self.write_register(instruction, 1, value)?;
+            },
+            OpCode::SHL => {
+                let value = self.read_register(instruction, 0)? as u64;
+                let amount = shift_amount(self.read_register(instruction, 1)?);
+                self.write_register(instruction, 2, value.checked_shl(amount).unwrap_or(0) as i64)?;
+            },
+            OpCode::SHR => {
+                let value = self.read_register(instruction, 0)? as u64;
+                let amount = shift_amount(self.read_register(instruction, 1)?);
+                self.write_register(instruction, 2, value.checked_shr(amount).unwrap_or(0) as i64)?;
+            },
+            OpCode::SAR => {
+                let value = self.read_register(instruction, 0)?;
+                let amount = shift_amount(self.read_register(instruction, 1)?);
+                self.write_register(instruction, 2, value >> amount.min(i64::BITS - 1))?;
             },
             OpCode::JMP => {
-                let value = self.registers[self.next_8_bits() as usize];
-                self.program_counter = value as usize;
+                let value = self.read_register(instruction, 0)?;
+                self.jump_to(value)?;
             },
             OpCode::JMPF => {
-                let value = self.registers[self.next_8_bits() as usize];
-                self.program_counter += value as usize;
+                let value = self.read_register(instruction, 0)?;
+                self.jump_to((self.program_counter as i64).saturating_add(value))?;
             },
             OpCode::JMPB => {
-                let value = self.registers[self.next_8_bits() as usize];
-                self.program_counter -= value as usize;
+                let value = self.read_register(instruction, 0)?;
+                self.jump_to((self.program_counter as i64).saturating_sub(value))?;
             },
             OpCode::EQ => {
-                let register1 = self.registers[self.next_8_bits() as usize];
-                let register2 = self.registers[self.next_8_bits() as usize];
-                self.equal_flag = register1 == register2;
-                self.next_8_bits();
+                self.equal_flag = self.read_register(instruction, 0)? == self.read_register(instruction, 1)?;
             },
             OpCode::NEQ => {
-                let register1 = self.registers[self.next_8_bits() as usize];
-                let register2 = self.registers[self.next_8_bits() as usize];
-                self.equal_flag = register1 != register2;
-                self.next_8_bits();
+                self.equal_flag = self.read_register(instruction, 0)? != self.read_register(instruction, 1)?;
             },
             OpCode::GT => {
-                let register1 = self.registers[self.next_8_bits() as usize];
-                let register2 = self.registers[self.next_8_bits() as usize];
-                self.equal_flag = register1 > register2;
-                self.next_8_bits();
+                self.equal_flag = self.read_register(instruction, 0)? > self.read_register(instruction, 1)?;
             },
             OpCode::LT => {
-                let register1 = self.registers[self.next_8_bits() as usize];
-                let register2 = self.registers[self.next_8_bits() as usize];
-                self.equal_flag = register1 < register2;
-                self.next_8_bits();
+                self.equal_flag = self.read_register(instruction, 0)? < self.read_register(instruction, 1)?;
             },
             OpCode::GTE => {
-                let register1 = self.registers[self.next_8_bits() as usize];
-                let register2 = self.registers[self.next_8_bits() as usize];
-                self.equal_flag = register1 >= register2;
-                self.next_8_bits();
+                self.equal_flag = self.read_register(instruction, 0)? >= self.read_register(instruction, 1)?;
             },
             OpCode::LTE => {
-                let register1 = self.registers[self.next_8_bits() as usize];
-                let register2 = self.registers[self.next_8_bits() as usize];
-                self.equal_flag = register1 <= register2;
-                self.next_8_bits();
+                self.equal_flag = self.read_register(instruction, 0)? <= self.read_register(instruction, 1)?;
+            },
+            OpCode::GTU => {
+                self.equal_flag = self.read_register(instruction, 0)? as u64 > self.read_register(instruction, 1)? as u64;
+            },
+            OpCode::LTU => {
+                self.equal_flag = (self.read_register(instruction, 0)? as u64) < self.read_register(instruction, 1)? as u64;
+            },
+            OpCode::GTEU => {
+                self.equal_flag = self.read_register(instruction, 0)? as u64 >= self.read_register(instruction, 1)? as u64;
+            },
+            OpCode::LTEU => {
+                self.equal_flag = self.read_register(instruction, 0)? as u64 <= self.read_register(instruction, 1)? as u64;
             },
             OpCode::JMPE => {
-                let register = self.next_8_bits() as usize;
-                let target = self.registers[register];
+                let target = self.read_register(instruction, 0)?;
                 if self.equal_flag {
-                    self.program_counter = target as usize;
+                    self.jump_to(target)?;
+                }
+            },
+            OpCode::JMPNE => {
+                let target = self.read_register(instruction, 0)?;
+                if !self.equal_flag {
+                    self.jump_to(target)?;
                 }
             },
             OpCode::PRINT => {
-                let register = self.next_8_bits() as usize;
-                print!("{}", self.registers[register]);
+                let value = self.read_register(instruction, 0)?;
+                write!(self.output, "{}", value).map_err(|e| VmErrorKind::Io(e.kind()))?;
             },
             OpCode::PRINTLN => {
-                let register = self.next_8_bits() as usize;
-                println!("{}", self.registers[register]);
+                let value = self.read_register(instruction, 0)?;
+                writeln!(self.output, "{}", value).map_err(|e| VmErrorKind::Io(e.kind()))?;
+            },
+            OpCode::CALL => {
+                let target = self.read_register(instruction, 0)?;
+                if self.call_stack.len() >= CALL_STACK_SIZE {
+                    return Err(VmErrorKind::StackOverflow);
+                }
+                // The return address is only pushed once the target is known to be valid, a faulting CALL leaves the stack as it was
+                let return_address = self.program_counter;
+                self.jump_to(target)?;
+                self.call_stack.push(return_address);
+            },
+            OpCode::RET => {
+                self.program_counter = self.call_stack.pop().ok_or(VmErrorKind::StackUnderflow)?;
             },
-            _ => {
-                println!("Unknown opcode encountered");
-                return true;
+            OpCode::PUSH => {
+                let value = self.read_register(instruction, 0)?;
+                if self.stack.len() >= STACK_SIZE {
+                    return Err(VmErrorKind::StackOverflow);
+                }
+                self.stack.push(value);
+            },
+            OpCode::POP => {
+                let value = self.stack.pop().ok_or(VmErrorKind::StackUnderflow)?;
+                self.write_register(instruction, 0, value)?;
+            },
+            OpCode::LDB => {
+                let address = self.read_register(instruction, 0)?;
+                let bytes = self.heap_slice(address, 1)?;
+                let value = bytes[0] as i64;
+                self.write_register(instruction, 1, value)?;
+            },
+            OpCode::LDH => {
+                let address = self.read_register(instruction, 0)?;
+                let bytes = self.heap_slice(address, 2)?;
+                let value = u16::from_be_bytes([bytes[0], bytes[1]]) as i64;
+                self.write_register(instruction, 1, value)?;
+            },
+            OpCode::LDBS => {
+                let address = self.read_register(instruction, 0)?;
+                let bytes = self.heap_slice(address, 1)?;
+                let value = bytes[0] as i8 as i64;
+                self.write_register(instruction, 1, value)?;
+            },
+            OpCode::LDHS => {
+                let address = self.read_register(instruction, 0)?;
+                let bytes = self.heap_slice(address, 2)?;
+                let value = i16::from_be_bytes([bytes[0], bytes[1]]) as i64;
+                self.write_register(instruction, 1, value)?;
+            },
+            OpCode::LDW => {
+                let address = self.read_register(instruction, 0)?;
+                let bytes = self.heap_slice(address, 4)?;
+                let value = i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64;
+                self.write_register(instruction, 1, value)?;
+            },
+            OpCode::LDD => {
+                let address = self.read_register(instruction, 0)?;
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(self.heap_slice(address, 8)?);
+                self.write_register(instruction, 1, i64::from_be_bytes(bytes))?;
+            },
+            OpCode::STB => {
+                let value = self.read_register(instruction, 0)?;
+                let address = self.read_register(instruction, 1)?;
+                self.heap_slice(address, 1)?.copy_from_slice(&(value as u8).to_be_bytes());
+            },
+            OpCode::STH => {
+                let value = self.read_register(instruction, 0)?;
+                let address = self.read_register(instruction, 1)?;
+                self.heap_slice(address, 2)?.copy_from_slice(&(value as u16).to_be_bytes());
+            },
+            OpCode::STW => {
+                let value = self.read_register(instruction, 0)?;
+                let address = self.read_register(instruction, 1)?;
+                self.heap_slice(address, 4)?.copy_from_slice(&(value as u32).to_be_bytes());
+            },
+            OpCode::STD => {
+                let value = self.read_register(instruction, 0)?;
+                let address = self.read_register(instruction, 1)?;
+                self.heap_slice(address, 8)?.copy_from_slice(&value.to_be_bytes());
+            },
+            OpCode::ALOC => {
+                let size = self.read_register(instruction, 0)?;
+                let new_size = (self.heap.len() as i64).saturating_add(size);
+                if size < 0 || new_size > MAX_HEAP_SIZE as i64 {
+                    return Err(VmErrorKind::AllocationFailed(size));
+                }
+                self.heap.resize(new_size as usize, 0);
+            },
+            OpCode::READ => {
+                let value = self.read_input_number()?;
+                self.equal_flag = value.is_some();
+                self.write_register(instruction, 0, value.unwrap_or(-1))?;
+            },
+            OpCode::READC => {
+                let value = self.read_input_byte()?;
+                self.equal_flag = value.is_some();
+                self.write_register(instruction, 0, value.map_or(-1, |byte| byte as i64))?;
+            },
+            OpCode::FSTORE => {
+                self.write_float_register(instruction, 0, float(instruction, 1))?;
+            },
+            OpCode::FADD => {
+                let value = self.read_float_register(instruction, 0)? + self.read_float_register(instruction, 1)?;
+                self.write_float_register(instruction, 2, value)?;
+            },
+            OpCode::FSUB => {
+                let value = self.read_float_register(instruction, 0)? - self.read_float_register(instruction, 1)?;
+                self.write_float_register(instruction, 2, value)?;
+            },
+            OpCode::FMUL => {
+                let value = self.read_float_register(instruction, 0)? * self.read_float_register(instruction, 1)?;
+                self.write_float_register(instruction, 2, value)?;
+            },
+            OpCode::FDIV => {
+                // Follows IEEE 754, a division by zero gives an infinity or NaN instead of a fault
+                let value = self.read_float_register(instruction, 0)? / self.read_float_register(instruction, 1)?;
+                self.write_float_register(instruction, 2, value)?;
+            },
+            OpCode::FEQ => {
+                self.equal_flag = self.read_float_register(instruction, 0)? == self.read_float_register(instruction, 1)?;
+            },
+            OpCode::FNEQ => {
+                self.equal_flag = self.read_float_register(instruction, 0)? != self.read_float_register(instruction, 1)?;
+            },
+            OpCode::FGT => {
+                self.equal_flag = self.read_float_register(instruction, 0)? > self.read_float_register(instruction, 1)?;
+            },
+            OpCode::FLT => {
+                self.equal_flag = self.read_float_register(instruction, 0)? < self.read_float_register(instruction, 1)?;
+            },
+            OpCode::FGTE => {
+                self.equal_flag = self.read_float_register(instruction, 0)? >= self.read_float_register(instruction, 1)?;
+            },
+            OpCode::FLTE => {
+                self.equal_flag = self.read_float_register(instruction, 0)? <= self.read_float_register(instruction, 1)?;
+            },
+            OpCode::ITOF => {
+                let value = self.read_register(instruction, 0)? as f64;
+                self.write_float_register(instruction, 1, value)?;
+            },
+            OpCode::FTOI => {
+                // Saturates to the bounds of an integer register, NaN becomes 0
+                let value = self.read_float_register(instruction, 0)? as i64;
+                self.write_register(instruction, 1, value)?;
+            },
+            OpCode::FPRINT => {
+                let value = self.read_float_register(instruction, 0)?;
+                write!(self.output, "{}", value).map_err(|e| VmErrorKind::Io(e.kind()))?;
+            },
+            OpCode::FPRINTLN => {
+                let value = self.read_float_register(instruction, 0)?;
+                writeln!(self.output, "{}", value).map_err(|e| VmErrorKind::Io(e.kind()))?;
+            },
+            OpCode::ILG => {
+                unreachable!("illegal opcodes are rejected by Instruction::decode");
             }
         }
-        return false;
+        Ok(ExitStatus::Running)
     }
-    /// Decodes the current byte and return the corresponding OpCode
-    fn decode_opcode(&mut self) -> OpCode {
-        let opcode = self.program[self.program_counter];
-        self.program_counter += 1;
-        OpCode::from(opcode)
+    /// Moves the program counter to `target`, which can be the end of the program but not past it
+    fn jump_to(&mut self, target: i64) -> Result<(), VmErrorKind> {
+        if target < 0 || target as usize > self.program.len() {
+            return Err(VmErrorKind::JumpOutOfBounds(target));
+        }
+        self.program_counter = target as usize;
+        Ok(())
     }
-    /// Returns the next 8 bits of the program
-    fn next_8_bits(&mut self) -> u8 {
-        let result = self.program[self.program_counter];
-        self.program_counter += 1;
-        result
+    /// Returns the next byte of the input, `None` at the end of the input
+    fn read_input_byte(&mut self) -> Result<Option<u8>, VmErrorKind> {
+        if let Some(byte) = self.peeked_input.pop() {
+            return Ok(Some(byte));
+        }
+        let mut byte = [0];
+        loop {
+            match self.input.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(VmErrorKind::Io(e.kind())),
+            }
+        }
     }
-    /// Returns the next 16 bits of the program
-    fn next_16_bits(&mut self) -> u16 {
-        let result = ((self.program[self.program_counter] as u16) << 8) | self.program[self.program_counter + 1] as u16;
-        self.program_counter += 2;
-        result
+    /// Skips whitespaces and reads a decimal integer from the input, `None` at the end of the input.
+    /// On a fault every byte read is given back to the input
+    fn read_input_number(&mut self) -> Result<Option<i64>, VmErrorKind> {
+        let mut read = Vec::new();
+        match self.read_input_number_into(&mut read) {
+            Ok((number, next)) => {
+                self.peeked_input.extend(next);
+                Ok(number)
+            },
+            Err(kind) => {
+                self.peeked_input.extend(read.into_iter().rev());
+                Err(kind)
+            },
+        }
+    }
+    /// Reads a number for `read_input_number`, every byte read is pushed to `read`.
+    /// Also returns the byte following the number, which isn't part of it
+    fn read_input_number_into(&mut self, read: &mut Vec<u8>) -> Result<(Option<i64>, Option<u8>), VmErrorKind> {
+        let mut next = |vm: &mut Self| -> Result<Option<u8>, VmErrorKind> {
+            let byte = vm.read_input_byte()?;
+            read.extend(byte);
+            Ok(byte)
+        };
+        let mut byte = next(self)?;
+        while byte.is_some_and(|byte| byte.is_ascii_whitespace()) {
+            byte = next(self)?;
+        }
+        let mut text = String::new();
+        if byte == Some(b'-') {
+            text.push('-');
+            byte = next(self)?;
+        }
+        while let Some(digit) = byte.filter(|byte| byte.is_ascii_digit()) {
+            text.push(digit as char);
+            byte = next(self)?;
+        }
+        if text.is_empty() && byte.is_none() {
+            return Ok((None, None));
+        }
+        text.parse().map(|number| (Some(number), byte)).map_err(|_| VmErrorKind::InvalidInput)
+    }
+    /// Returns the `width` bytes of the heap starting at `address`
+    fn heap_slice(&mut self, address: i64, width: usize) -> Result<&mut [u8], VmErrorKind> {
+        if address < 0 || address as u64 + width as u64 > self.heap.len() as u64 {
+            return Err(VmErrorKind::HeapOutOfBounds(address));
+        }
+        Ok(&mut self.heap[address as usize..address as usize + width])
+    }
+    /// Decodes the instruction at the program counter with the operand layout of reg-byte
+    fn decode_instruction(&self) -> Result<Instruction, VmErrorKind> {
+        Instruction::decode(&self.program, self.program_counter).map_err(|e| match e {
+            DecodeError::IllegalOpcode { byte, .. } => VmErrorKind::IllegalOpcode(byte),
+            DecodeError::Truncated { .. } | DecodeError::EndOfProgram { .. } => VmErrorKind::UnexpectedEndOfProgram,
+        })
+    }
+    /// Returns the value of the register given as operand `index`
+    fn read_register(&self, instruction: &Instruction, index: usize) -> Result<i64, VmErrorKind> {
+        Ok(self.registers[register(instruction, index)?])
+    }
+    /// Stores `value` in the register given as operand `index`
+    fn write_register(&mut self, instruction: &Instruction, index: usize, value: i64) -> Result<(), VmErrorKind> {
+        let register = register(instruction, index)?;
+        self.registers[register] = value;
+        if self.tracer.is_some() {
+            self.traced_writes.push((register, value));
+        }
+        Ok(())
+    }
+    /// Returns the value of the float register given as operand `index`
+    fn read_float_register(&self, instruction: &Instruction, index: usize) -> Result<f64, VmErrorKind> {
+        Ok(self.float_registers[float_register(instruction, index)?])
+    }
+    /// Stores `value` in the float register given as operand `index`
+    fn write_float_register(&mut self, instruction: &Instruction, index: usize, value: f64) -> Result<(), VmErrorKind> {
+        let register = float_register(instruction, index)?;
+        self.float_registers[register] = value;
+        if self.tracer.is_some() {
+            self.traced_float_writes.push((register, value));
+        }
+        Ok(())
+    }
+}
+
+/// Checks that every register operand is one of the VM registers
+fn check_registers(instruction: &Instruction) -> Result<(), VmErrorKind> {
+    for operand in instruction.operands() {
+        match *operand {
+            Operand::Register(register) if register as usize >= REGISTER_COUNT => return Err(VmErrorKind::InvalidRegister(register)),
+            Operand::FloatRegister(register) if register as usize >= REGISTER_COUNT => {
+                return Err(VmErrorKind::InvalidFloatRegister(register));
+            },
+            _ => {},
+        }
+    }
+    Ok(())
+}
+
+/// Returns the operand `index` as an index in the registers
+fn register(instruction: &Instruction, index: usize) -> Result<usize, VmErrorKind> {
+    match instruction.operands()[index] {
+        Operand::Register(register) if (register as usize) < REGISTER_COUNT => Ok(register as usize),
+        Operand::Register(register) => Err(VmErrorKind::InvalidRegister(register)),
+        operand => unreachable!("{} isn't a register operand", operand),
+    }
+}
+
+/// Returns the operand `index` as an immediate
+fn immediate(instruction: &Instruction, index: usize) -> i32 {
+    match instruction.operands()[index] {
+        Operand::Immediate(number) => number,
+        operand => unreachable!("{} isn't an immediate operand", operand),
     }
-}
\ No newline at end of file
+}
+
+/// Returns the operand `index` as an index in the float registers
+fn float_register(instruction: &Instruction, index: usize) -> Result<usize, VmErrorKind> {
+    match instruction.operands()[index] {
+        Operand::FloatRegister(register) if (register as usize) < REGISTER_COUNT => Ok(register as usize),
+        Operand::FloatRegister(register) => Err(VmErrorKind::InvalidFloatRegister(register)),
+        operand => unreachable!("{} isn't a float register operand", operand),
+    }
+}
+
+/// Returns the operand `index` as an immediate float
+fn float(instruction: &Instruction, index: usize) -> f64 {
+    match instruction.operands()[index] {
+        Operand::Float(number) => number,
+        operand => unreachable!("{} isn't a float operand", operand),
+    }
+}
+
+/// Number of bits to shift by, the shift amount is unsigned and shifting by 64 bits or more shifts every bit out
+fn shift_amount(amount: i64) -> u32 {
+    u32::try_from(amount as u64).unwrap_or(u32::MAX)
+}
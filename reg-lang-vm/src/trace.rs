@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+
+use reg_byte::Instruction;
+
+/// What an executed instruction did
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    /// Byte offset of the instruction
+    pub program_counter: usize,
+    pub instruction: Instruction,
+    /// Registers written by the instruction with their new value, in order
+    pub register_writes: Vec<(usize, i64)>,
+    /// Float registers written by the instruction with their new value, in order
+    pub float_register_writes: Vec<(usize, f64)>,
+    /// New value of the equal flag, if the instruction changed it
+    pub equal_flag: Option<bool>,
+    /// New value of the remainder, if the instruction changed it
+    pub remainder: Option<u64>,
+    /// New value of the carry flag, if the instruction changed it
+    pub carry_flag: Option<bool>,
+    /// New value of the overflow flag, if the instruction changed it
+    pub overflow_flag: Option<bool>,
+}
+
+/// Receives every instruction executed by a `RegLangVM`.
+/// An error stops the VM with `VmErrorKind::Io` once the traced instruction executed,
+/// the `VmError` then has the program counter of the next instruction, where the VM resumes, and the opcode of the traced one
+pub trait Tracer {
+    fn trace(&mut self, event: &TraceEvent) -> io::Result<()>;
+}
+
+/// Writes one human readable line per instruction: `0029  ADD $0 $2 $0  ; $0 = 6`
+pub struct TextTracer<W: Write> {
+    writer: W,
+}
+impl<W: Write> TextTracer<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+impl<W: Write> Tracer for TextTracer<W> {
+    fn trace(&mut self, event: &TraceEvent) -> io::Result<()> {
+        let mut changes = Vec::new();
+        for (register, value) in &event.register_writes {
+            changes.push(format!("${} = {}", register, value));
+        }
+        for (register, value) in &event.float_register_writes {
+            changes.push(format!("$f{} = {}", register, value));
+        }
+        if let Some(equal_flag) = event.equal_flag {
+            changes.push(format!("equal = {}", equal_flag));
+        }
+        if let Some(remainder) = event.remainder {
+            changes.push(format!("remainder = {}", remainder));
+        }
+        if let Some(carry_flag) = event.carry_flag {
+            changes.push(format!("carry = {}", carry_flag));
+        }
+        if let Some(overflow_flag) = event.overflow_flag {
+            changes.push(format!("overflow = {}", overflow_flag));
+        }
+        if changes.is_empty() {
+            writeln!(self.writer, "{:04}  {}", event.program_counter, event.instruction)
+        } else {
+            writeln!(self.writer, "{:04}  {}  ; {}", event.program_counter, event.instruction, changes.join(", "))
+        }
+    }
+}
+
+/// Writes one JSON object per instruction:
+/// `{"pc":29,"opcode":"ADD","operands":["$0","$2","$0"],"writes":[{"register":0,"value":6}]}`,
+/// with `float_writes` when float registers were written and `equal_flag`, `remainder`, `carry_flag` and `overflow_flag` keys when they changed.
+/// Infinite and NaN floats are written as `null`
+pub struct JsonTracer<W: Write> {
+    writer: W,
+}
+impl<W: Write> JsonTracer<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+impl<W: Write> Tracer for JsonTracer<W> {
+    fn trace(&mut self, event: &TraceEvent) -> io::Result<()> {
+        let operands: Vec<String> = event.instruction.operands().iter().map(|operand| format!("\"{}\"", operand)).collect();
+        let writes: Vec<String> = event.register_writes.iter()
+            .map(|(register, value)| format!("{{\"register\":{},\"value\":{}}}", register, value))
+            .collect();
+        write!(
+            self.writer,
+            "{{\"pc\":{},\"opcode\":\"{}\",\"operands\":[{}],\"writes\":[{}]",
+            event.program_counter,
+            event.instruction.opcode.mnemonic(),
+            operands.join(","),
+            writes.join(","),
+        )?;
+        if !event.float_register_writes.is_empty() {
+            let float_writes: Vec<String> = event.float_register_writes.iter()
+                .map(|(register, value)| match value.is_finite() {
+                    true => format!("{{\"register\":{},\"value\":{:?}}}", register, value),
+                    false => format!("{{\"register\":{},\"value\":null}}", register),
+                })
+                .collect();
+            write!(self.writer, ",\"float_writes\":[{}]", float_writes.join(","))?;
+        }
+        if let Some(equal_flag) = event.equal_flag {
+            write!(self.writer, ",\"equal_flag\":{}", equal_flag)?;
+        }
+        if let Some(remainder) = event.remainder {
+            write!(self.writer, ",\"remainder\":{}", remainder)?;
+        }
+        if let Some(carry_flag) = event.carry_flag {
+            write!(self.writer, ",\"carry_flag\":{}", carry_flag)?;
+        }
+        if let Some(overflow_flag) = event.overflow_flag {
+            write!(self.writer, ",\"overflow_flag\":{}", overflow_flag)?;
+        }
+        writeln!(self.writer, "}}")
+    }
+}
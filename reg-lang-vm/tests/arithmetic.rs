@@ -0,0 +1,84 @@
+mod common;
+
+use common::{assemble, run_with};
+use reg_byte::OpCode;
+use reg_lang_vm::{VmError, VmErrorKind};
+
+/// Result in `$2` and carry and overflow flags of `operation $0 $1 $2` with `$0 = left` and `$1 = right`
+fn binary(operation: &str, left: i64, right: i64) -> (i64, bool, bool) {
+    let vm = run_with(&format!("{} $0 $1 $2", operation), &[(0, left), (1, right)]);
+    (vm.registers()[2], vm.carry_flag(), vm.overflow_flag())
+}
+
+#[test]
+fn add_sets_carry_on_unsigned_wrap_and_overflow_on_signed_wrap() {
+    assert_eq!(binary("ADD", 2, 3), (5, false, false));
+    assert_eq!(binary("ADD", -1, 1), (0, true, false));
+    assert_eq!(binary("ADD", i64::MAX, 1), (i64::MIN, false, true));
+    assert_eq!(binary("ADD", i64::MIN, -1), (i64::MAX, true, true));
+}
+
+#[test]
+fn sub_sets_carry_on_borrow_and_overflow_on_signed_wrap() {
+    assert_eq!(binary("SUB", 5, 3), (2, false, false));
+    assert_eq!(binary("SUB", 0, 1), (-1, true, false));
+    assert_eq!(binary("SUB", i64::MIN, 1), (i64::MAX, false, true));
+    assert_eq!(binary("SUB", 0, i64::MIN), (i64::MIN, true, true));
+}
+
+#[test]
+fn mul_sets_carry_and_overflow_independently() {
+    assert_eq!(binary("MUL", -3, 4), (-12, true, false));
+    assert_eq!(binary("MUL", i64::MAX, 2), (-2, false, true));
+    assert_eq!(binary("MUL", 1 << 32, 1 << 32), (0, true, true));
+    assert_eq!(binary("MUL", 6, 7), (42, false, false));
+}
+
+#[test]
+fn neg_of_i64_min_overflows() {
+    let vm = run_with("NEG $0 $1", &[(0, i64::MIN)]);
+    assert_eq!(vm.registers()[1], i64::MIN);
+    assert!(vm.carry_flag() && vm.overflow_flag());
+    let vm = run_with("NEG $0 $1", &[(0, 0)]);
+    assert_eq!(vm.registers()[1], 0);
+    assert!(!vm.carry_flag() && !vm.overflow_flag());
+}
+
+#[test]
+fn div_of_i64_min_by_minus_one_overflows() {
+    let vm = run_with("DIV $0 $1 $2\nGETREM $3", &[(0, i64::MIN), (1, -1)]);
+    assert_eq!(vm.registers()[2..4], [i64::MIN, 0]);
+    assert!(!vm.carry_flag() && vm.overflow_flag());
+}
+
+#[test]
+fn divu_treats_registers_as_unsigned() {
+    let vm = run_with("DIVU $0 $1 $2\nGETREM $3", &[(0, -1), (1, 2)]);
+    assert_eq!(vm.registers()[2..4], [i64::MAX, 1]);
+    assert!(!vm.carry_flag() && !vm.overflow_flag());
+    let vm = run_with("DIV $0 $1 $2\nGETREM $3", &[(0, -1), (1, 2)]);
+    assert_eq!(vm.registers()[2..4], [0, -1]);
+    let vm = run_with("DIVU $0 $1 $2", &[(0, 5), (1, -1)]);
+    assert_eq!(vm.registers()[2], 0);
+}
+
+#[test]
+fn divu_by_zero_faults() {
+    let mut vm = assemble("STORE $0 #1\nDIVU $0 $1 $2");
+    let error = VmError { program_counter: 6, opcode: OpCode::DIVU, kind: VmErrorKind::DivisionByZero };
+    assert_eq!(vm.run(), Err(error));
+}
+
+#[test]
+fn unsigned_compares_treat_negative_values_as_large() {
+    for (comparison, signed, unsigned) in [("GT", false, true), ("LT", true, false), ("GTE", false, true), ("LTE", true, false)] {
+        let vm = run_with(&format!("{} $0 $1", comparison), &[(0, -1), (1, 1)]);
+        assert_eq!(vm.equal_flag(), signed, "{}", comparison);
+        let vm = run_with(&format!("{}U $0 $1", comparison), &[(0, -1), (1, 1)]);
+        assert_eq!(vm.equal_flag(), unsigned, "{}U", comparison);
+    }
+    for comparison in ["GTEU", "LTEU"] {
+        assert!(run_with(&format!("{} $0 $1", comparison), &[(0, i64::MIN), (1, i64::MIN)]).equal_flag(), "{}", comparison);
+    }
+    assert!(run_with("GTU $0 $1", &[(0, i64::MIN), (1, i64::MAX)]).equal_flag());
+}
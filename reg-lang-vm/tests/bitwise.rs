@@ -0,0 +1,52 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn and_keeps_the_bits_set_in_both_registers() {
+    let registers = run("STORE $0 #0b1100\nSTORE $1 #0b1010\nAND $0 $1 $2");
+    assert_eq!(registers[2], 0b1000);
+}
+
+#[test]
+fn or_keeps_the_bits_set_in_either_register() {
+    let registers = run("STORE $0 #0b1100\nSTORE $1 #0b1010\nOR $0 $1 $2");
+    assert_eq!(registers[2], 0b1110);
+}
+
+#[test]
+fn xor_keeps_the_bits_set_in_only_one_register() {
+    let registers = run("STORE $0 #0b1100\nSTORE $1 #0b1010\nXOR $0 $1 $2");
+    assert_eq!(registers[2], 0b0110);
+}
+
+#[test]
+fn not_flips_every_bit() {
+    let registers = run("STORE $0 #0\nSTORE $1 #0x0F\nNOT $0 $2\nNOT $1 $3");
+    assert_eq!(registers[2], -1);
+    assert_eq!(registers[3], !0x0F);
+}
+
+#[test]
+fn shl_shifts_in_zeros() {
+    let registers = run("STORE $0 #3\nSTORE $1 #4\nSHL $0 $1 $2\nSTORE $3 #63\nSHL $0 $3 $4\nSTORE $5 #64\nSHL $0 $5 $6");
+    assert_eq!(registers[2], 48);
+    assert_eq!(registers[4], i64::MIN);
+    assert_eq!(registers[6], 0);
+}
+
+#[test]
+fn shr_shifts_in_zeros() {
+    let registers = run("STORE $0 #-16\nSTORE $1 #2\nSHR $0 $1 $2\nSTORE $3 #64\nSHR $0 $3 $4\nSTORE $5 #-1\nSHR $0 $5 $6");
+    assert_eq!(registers[2], (-16i64 as u64 >> 2) as i64);
+    assert_eq!(registers[4], 0);
+    assert_eq!(registers[6], 0);
+}
+
+#[test]
+fn sar_shifts_in_the_sign_bit() {
+    let registers = run("STORE $0 #-16\nSTORE $1 #2\nSAR $0 $1 $2\nSTORE $3 #100\nSAR $0 $3 $4\nSTORE $5 #16\nSAR $5 $3 $6");
+    assert_eq!(registers[2], -4);
+    assert_eq!(registers[4], -1);
+    assert_eq!(registers[6], 0);
+}
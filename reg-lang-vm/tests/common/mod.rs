@@ -0,0 +1,43 @@
+//! Helpers shared by the integration tests of the VM, every test file only uses some of them
+#![allow(dead_code)]
+
+use std::io;
+
+use reg_byte::REGISTER_COUNT;
+use reg_lang_compiler::RegCompiler;
+use reg_lang_vm::{ExitStatus, RegLangVM};
+
+/// Program counting in `$1` forever, the loop starts at byte 0
+pub const ENDLESS: &str = "loop:\nSTORE $0 #loop\nINC $1\nJMP $0";
+
+/// Assembles `source`, panicking with the compile error
+pub fn program(source: &str) -> Vec<u8> {
+    RegCompiler::compile(source).unwrap_or_else(|e| panic!("{}", e)).program
+}
+
+/// VM loaded with the program, writing to nowhere
+pub fn vm(program: Vec<u8>) -> RegLangVM {
+    RegLangVM::new(program).with_output(io::sink())
+}
+
+/// VM loaded with the assembled source, writing to nowhere
+pub fn assemble(source: &str) -> RegLangVM {
+    vm(program(source))
+}
+
+/// Runs `source` to the end and returns the registers
+pub fn run(source: &str) -> [i64; REGISTER_COUNT] {
+    let mut vm = assemble(source);
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    *vm.registers()
+}
+
+/// Runs `source` to the end with the given registers set first, and returns the VM
+pub fn run_with(source: &str, registers: &[(usize, i64)]) -> RegLangVM {
+    let mut vm = assemble(source);
+    for &(register, value) in registers {
+        vm.set_register(register, value);
+    }
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    vm
+}
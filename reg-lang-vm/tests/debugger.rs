@@ -0,0 +1,74 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::{assemble, ENDLESS};
+use reg_lang_vm::{BreakpointError, Debugger, ExitStatus};
+
+fn debugger(source: &str) -> Debugger {
+    Debugger::new(assemble(source), HashMap::new())
+}
+
+#[test]
+fn resume_stops_on_a_breakpoint_at_the_entry() {
+    let mut debugger = debugger(ENDLESS);
+    assert_eq!(debugger.add_breakpoint("0"), Ok(0));
+    assert_eq!(debugger.resume(), Ok(ExitStatus::Breakpoint(0)));
+    assert_eq!(debugger.vm.registers()[1], 0);
+}
+
+#[test]
+fn resume_skips_only_the_breakpoint_it_is_stopped_on() {
+    let mut debugger = debugger(ENDLESS);
+    debugger.add_breakpoint("0").unwrap();
+    assert_eq!(debugger.resume(), Ok(ExitStatus::Breakpoint(0)));
+    assert_eq!(debugger.resume(), Ok(ExitStatus::Breakpoint(0)));
+    assert_eq!(debugger.vm.registers()[1], 1);
+    assert_eq!(debugger.resume(), Ok(ExitStatus::Breakpoint(0)));
+    assert_eq!(debugger.vm.registers()[1], 2);
+}
+
+#[test]
+fn resume_after_a_step_onto_a_breakpoint_runs_it() {
+    let mut debugger = debugger(ENDLESS);
+    assert_eq!(debugger.step(), Ok(ExitStatus::Running));
+    let increment = debugger.vm.program_counter();
+    debugger.add_breakpoint(&increment.to_string()).unwrap();
+    assert_eq!(debugger.resume(), Ok(ExitStatus::Breakpoint(increment)));
+    assert_eq!(debugger.vm.registers()[1], 1);
+}
+
+#[test]
+fn breakpoints_must_be_at_an_instruction() {
+    // STORE is 6 bytes wide, INC 2 and HLT 1
+    let mut debugger = Debugger::new(assemble("STORE $0 #1\nloop:\nINC $0\nHLT"), HashMap::from([("loop".to_string(), 6)]));
+    assert_eq!(debugger.add_breakpoint("loop"), Ok(6));
+    assert_eq!(debugger.add_breakpoint("8"), Ok(8));
+    assert_eq!(debugger.add_breakpoint("3"), Err(BreakpointError::InsideInstruction(3)));
+    assert_eq!(debugger.add_breakpoint("9"), Err(BreakpointError::OutsideProgram(9)));
+    assert_eq!(debugger.add_breakpoint("1000"), Err(BreakpointError::OutsideProgram(1000)));
+    assert_eq!(debugger.add_breakpoint("end"), Err(BreakpointError::UnknownLocation("end".to_string())));
+    assert_eq!(debugger.breakpoints().collect::<Vec<_>>(), [6, 8]);
+}
+
+#[test]
+fn a_halted_program_does_not_run_past_hlt() {
+    let mut debugger = debugger("HLT\nINC $0\nHLT");
+    assert_eq!(debugger.resume(), Ok(ExitStatus::Halted));
+    assert!(debugger.is_halted());
+    assert_eq!(debugger.resume(), Ok(ExitStatus::Halted));
+    assert_eq!(debugger.step(), Ok(ExitStatus::Halted));
+    assert_eq!(debugger.vm.program_counter(), 1);
+    assert_eq!(debugger.vm.registers()[0], 0);
+}
+
+#[test]
+fn moving_the_program_counter_after_hlt_runs_again() {
+    let mut debugger = debugger("STORE $0 #1\nHLT");
+    assert_eq!(debugger.step(), Ok(ExitStatus::Running));
+    assert_eq!(debugger.step(), Ok(ExitStatus::Halted));
+    debugger.vm.set_program_counter(0);
+    assert!(!debugger.is_halted());
+    assert_eq!(debugger.resume(), Ok(ExitStatus::Halted));
+    assert_eq!(debugger.vm.program_counter(), 7);
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use std::io;
+
+use common::{assemble, vm};
+use reg_byte::OpCode;
+use reg_lang_vm::{ExitStatus, TraceEvent, Tracer, VmError, VmErrorKind};
+
+#[test]
+fn division_by_zero_faults_on_the_division() {
+    let mut vm = assemble("STORE $0 #1\nSTORE $1 #0\nDIV $0 $1 $2");
+    let error = VmError { program_counter: 12, opcode: OpCode::DIV, kind: VmErrorKind::DivisionByZero };
+    assert_eq!(vm.run(), Err(error));
+    assert_eq!(vm.program_counter(), 12);
+}
+
+#[test]
+fn invalid_register_byte_faults() {
+    let mut vm = vm(vec![OpCode::STORE as u8, 32, 0, 0, 0, 1]);
+    let error = VmError { program_counter: 0, opcode: OpCode::STORE, kind: VmErrorKind::InvalidRegister(32) };
+    assert_eq!(vm.run(), Err(error));
+}
+
+#[test]
+fn truncated_program_faults() {
+    let mut vm = vm(vec![OpCode::STORE as u8, 0, 0]);
+    let error = VmError { program_counter: 0, opcode: OpCode::STORE, kind: VmErrorKind::UnexpectedEndOfProgram };
+    assert_eq!(vm.run(), Err(error));
+}
+
+#[test]
+fn jmpb_before_the_start_faults() {
+    let mut vm = assemble("STORE $0 #100\nJMPB $0");
+    let error = VmError { program_counter: 6, opcode: OpCode::JMPB, kind: VmErrorKind::JumpOutOfBounds(8 - 100) };
+    assert_eq!(vm.run(), Err(error));
+    assert_eq!(vm.program_counter(), 6);
+}
+
+#[test]
+fn illegal_opcode_faults() {
+    let mut vm = vm(vec![OpCode::HLT as u8, 0xFF]);
+    vm.set_program_counter(1);
+    let error = VmError { program_counter: 1, opcode: OpCode::ILG, kind: VmErrorKind::IllegalOpcode(0xFF) };
+    assert_eq!(vm.run(), Err(error));
+}
+
+#[test]
+fn call_out_of_the_program_leaves_the_call_stack_unchanged() {
+    let mut vm = assemble("STORE $0 #1000\nCALL $0\nHLT\nsub:\nRET");
+    let error = VmError { program_counter: 6, opcode: OpCode::CALL, kind: VmErrorKind::JumpOutOfBounds(1000) };
+    assert_eq!(vm.run(), Err(error));
+    assert_eq!(vm.run(), Err(error));
+    assert!(vm.call_stack().is_empty());
+    vm.set_register(0, 9);
+    assert_eq!(vm.run(), Ok(ExitStatus::Halted));
+    assert!(vm.call_stack().is_empty());
+}
+
+#[test]
+fn invalid_destination_register_leaves_the_flags_unchanged() {
+    for opcode in [OpCode::ADD, OpCode::SUB, OpCode::MUL] {
+        let mut vm = vm(vec![opcode as u8, 0, 1, 40]);
+        vm.set_register(0, i64::MIN);
+        vm.set_register(1, -1);
+        let error = VmError { program_counter: 0, opcode, kind: VmErrorKind::InvalidRegister(40) };
+        assert_eq!(vm.run(), Err(error));
+        assert!(!vm.carry_flag() && !vm.overflow_flag(), "{}", opcode.mnemonic());
+    }
+    let mut vm = vm(vec![OpCode::NEG as u8, 0, 40]);
+    vm.set_register(0, i64::MIN);
+    assert_eq!(vm.run(), Err(VmError { program_counter: 0, opcode: OpCode::NEG, kind: VmErrorKind::InvalidRegister(40) }));
+    assert!(!vm.carry_flag() && !vm.overflow_flag());
+}
+
+#[test]
+fn read_into_an_invalid_register_leaves_the_input_and_the_flag_unchanged() {
+    let mut vm = vm(vec![OpCode::READ as u8, 40]).with_input(Box::new(&b"5"[..]));
+    vm.set_equal_flag(true);
+    let error = VmError { program_counter: 0, opcode: OpCode::READ, kind: VmErrorKind::InvalidRegister(40) };
+    assert_eq!(vm.run(), Err(error));
+    assert!(vm.equal_flag());
+    vm.load(vec![OpCode::READ as u8, 0]);
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(vm.registers()[0], 5);
+}
+
+#[test]
+fn failing_tracer_stops_after_the_traced_instruction() {
+    struct FailingTracer;
+    impl Tracer for FailingTracer {
+        fn trace(&mut self, _: &TraceEvent) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+    }
+    let mut vm = assemble("INC $0\nINC $0").with_tracer(Box::new(FailingTracer));
+    let error = vm.run().unwrap_err();
+    assert_eq!(error, VmError { program_counter: 2, opcode: OpCode::INC, kind: VmErrorKind::Io(io::ErrorKind::BrokenPipe) });
+    assert_eq!(vm.program_counter(), error.program_counter);
+    assert_eq!(vm.registers()[0], 1);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::assemble;
+use reg_lang_vm::{ExitStatus, RegLangVM};
+
+/// Runs `source` to the end and returns the VM
+fn run(source: &str) -> RegLangVM {
+    let mut vm = assemble(source);
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    vm
+}
+
+#[test]
+fn float_arithmetic() {
+    let vm = run("FSTORE $f0 #1.5\nFSTORE $f1 #-4.0\nFADD $f0 $f1 $f2\nFSUB $f0 $f1 $f3\nFMUL $f0 $f1 $f4\nFDIV $f1 $f0 $f5");
+    assert_eq!(vm.float_registers()[2..6], [-2.5, 5.5, -6.0, -4.0 / 1.5]);
+}
+
+#[test]
+fn fdiv_by_zero_follows_ieee_754() {
+    let vm = run("FSTORE $f1 #1.0\nFSTORE $f2 #-1.0\nFDIV $f1 $f0 $f3\nFDIV $f2 $f0 $f4\nFDIV $f0 $f0 $f5");
+    assert_eq!(vm.float_registers()[3], f64::INFINITY);
+    assert_eq!(vm.float_registers()[4], f64::NEG_INFINITY);
+    assert!(vm.float_registers()[5].is_nan());
+}
+
+#[test]
+fn float_compares() {
+    for (comparison, less, equal) in [("FEQ", false, true), ("FNEQ", true, false), ("FGT", false, false), ("FLT", true, false), ("FGTE", false, true), ("FLTE", true, true)] {
+        let vm = run(&format!("FSTORE $f0 #-0.5\nFSTORE $f1 #2.25\n{} $f0 $f1", comparison));
+        assert_eq!(vm.equal_flag(), less, "{} with a lower value", comparison);
+        let vm = run(&format!("FSTORE $f0 #2.25\nFSTORE $f1 #2.25\n{} $f0 $f1", comparison));
+        assert_eq!(vm.equal_flag(), equal, "{} with an equal value", comparison);
+    }
+}
+
+#[test]
+fn nan_is_only_different() {
+    for (comparison, result) in [("FEQ", false), ("FNEQ", true), ("FGT", false), ("FLT", false), ("FGTE", false), ("FLTE", false)] {
+        let vm = run(&format!("FDIV $f0 $f0 $f1\n{} $f1 $f1", comparison));
+        assert_eq!(vm.equal_flag(), result, "{}", comparison);
+    }
+}
+
+#[test]
+fn itof_converts_to_the_nearest_float() {
+    let mut vm = assemble("ITOF $0 $f0\nITOF $1 $f1");
+    vm.set_register(0, -7);
+    vm.set_register(1, i64::MAX);
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(vm.float_registers()[0..2], [-7.0, 9_223_372_036_854_775_808.0]);
+}
+
+#[test]
+fn ftoi_truncates_and_saturates() {
+    let mut vm = assemble("FTOI $f0 $0\nFTOI $f1 $1\nFTOI $f2 $2\nFTOI $f3 $3\nFTOI $f4 $4\nFTOI $f5 $5");
+    for (register, value) in [-2.7, f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1e300, 9.9].into_iter().enumerate() {
+        vm.set_float_register(register, value);
+    }
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(vm.registers()[0..6], [-2, 0, i64::MAX, i64::MIN, i64::MAX, 9]);
+}
+
+#[test]
+fn fstore_converts_an_integer_expression() {
+    let vm = run(".equ N 3\nFSTORE $f0 #N * 2 + 1\nFSTORE $f1 #-N\nFSTORE $f2 #0x10");
+    assert_eq!(vm.float_registers()[0..3], [7.0, -3.0, 16.0]);
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::assemble;
+use reg_byte::OpCode;
+use reg_lang_vm::{ExitStatus, RegLangVM, VmError, VmErrorKind};
+
+/// VM running `source` with `input` as its input
+fn vm(source: &str, input: &'static str) -> RegLangVM {
+    assemble(source).with_input(Box::new(input.as_bytes()))
+}
+
+#[test]
+fn read_parses_integers_until_the_end_of_the_input() {
+    let mut vm = vm("READ $0\nREAD $1\nREAD $2", " 12\n-3\n");
+    assert_eq!(vm.run_once(), Ok(ExitStatus::Running));
+    assert!(vm.equal_flag());
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(vm.registers()[..3], [12, -3, -1]);
+    assert!(!vm.equal_flag());
+}
+
+#[test]
+fn read_faults_on_something_else_than_an_integer() {
+    let mut vm = vm("READ $0", "abc");
+    let error = VmError { program_counter: 0, opcode: OpCode::READ, kind: VmErrorKind::InvalidInput };
+    assert_eq!(vm.run(), Err(error));
+}
+
+#[test]
+fn readc_reads_bytes_until_the_end_of_the_input() {
+    let mut vm = vm("READC $0\nREADC $1", "A");
+    assert_eq!(vm.run_once(), Ok(ExitStatus::Running));
+    assert!(vm.equal_flag());
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(vm.registers()[..2], [b'A' as i64, -1]);
+    assert!(!vm.equal_flag());
+}
+
+#[test]
+fn read_gives_back_what_it_read_when_it_faults() {
+    let mut vm = vm("READ $0\nREADC $1", " -x");
+    assert_eq!(vm.run_once(), Err(VmError { program_counter: 0, opcode: OpCode::READ, kind: VmErrorKind::InvalidInput }));
+    vm.set_program_counter(2);
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(vm.registers()[1], b' ' as i64);
+}
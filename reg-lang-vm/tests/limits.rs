@@ -0,0 +1,34 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{assemble, ENDLESS};
+use reg_lang_vm::ExitStatus;
+
+#[test]
+fn run_with_limit_resumes_where_it_stopped() {
+    let mut vm = assemble(ENDLESS);
+    assert_eq!(vm.run_with_limit(3), Ok(ExitStatus::OutOfFuel));
+    assert_eq!(vm.registers()[1], 1);
+    assert_eq!(vm.run_with_limit(2), Ok(ExitStatus::OutOfFuel));
+    assert_eq!(vm.registers()[1], 2);
+    assert_eq!(vm.program_counter(), 8);
+    assert_eq!(vm.run_with_limit(30), Ok(ExitStatus::OutOfFuel));
+    assert_eq!(vm.registers()[1], 12);
+}
+
+#[test]
+fn run_with_limit_reports_the_end_of_the_program() {
+    assert_eq!(assemble("INC $0\nINC $0").run_with_limit(2), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(assemble("INC $0\nHLT\nINC $0").run_with_limit(5), Ok(ExitStatus::Halted));
+}
+
+#[test]
+fn run_with_timeout_stops_an_endless_program() {
+    let mut vm = assemble(ENDLESS);
+    assert_eq!(vm.run_with_timeout(Duration::from_millis(10)), Ok(ExitStatus::OutOfTime));
+    let count = vm.registers()[1];
+    assert!(count > 0);
+    assert_eq!(vm.run_with_timeout(Duration::from_millis(10)), Ok(ExitStatus::OutOfTime));
+    assert!(vm.registers()[1] > count);
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::{assemble, program};
+use reg_lang_vm::{ExitStatus, VmErrorKind};
+
+#[test]
+fn load_restarts_from_the_first_byte_and_keeps_the_registers() {
+    let mut vm = assemble("STORE $0 #5");
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    vm.load(program("INC $0"));
+    assert_eq!(vm.program_counter(), 0);
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(vm.registers()[0], 6);
+}
+
+#[test]
+fn load_keeps_the_flags_the_stack_and_the_heap() {
+    let mut vm = assemble("STORE $0 #8\nALOC $0\nSTORE $1 #-3\nSTD $1 $2\nPUSH $1\nEQ $1 $1");
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    vm.load(program("POP $3\nLDD $2 $4\nGETFLAG $5"));
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(vm.registers()[3..6], [-3, -3, 1]);
+}
+
+#[test]
+fn load_drops_the_return_addresses_and_the_fault() {
+    let mut vm = assemble("STORE $0 #sub\nCALL $0\nsub:\nDIV $0 $1 $2");
+    assert_eq!(vm.run().map_err(|error| error.kind), Err(VmErrorKind::DivisionByZero));
+    assert_eq!(vm.call_stack().len(), 1);
+    vm.load(program("INC $1"));
+    assert!(vm.call_stack().is_empty());
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(vm.registers()[1], 1);
+}
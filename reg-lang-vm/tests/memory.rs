@@ -0,0 +1,88 @@
+mod common;
+
+use common::{assemble, program, run, run_with};
+use reg_byte::OpCode;
+use reg_lang_vm::{ExitStatus, VmError, VmErrorKind, MAX_HEAP_SIZE};
+
+#[test]
+fn narrow_loads_zero_or_sign_extend() {
+    let registers = run("STORE $0 #8\nALOC $0\nSTORE $1 #-2\nSTORE $2 #0\nSTH $1 $2\nLDH $2 $3\nLDHS $2 $4\nLDB $2 $5\nLDBS $2 $6");
+    assert_eq!(registers[3], 0xFFFE);
+    assert_eq!(registers[4], -2);
+    assert_eq!(registers[5], 0xFF);
+    assert_eq!(registers[6], -1);
+}
+
+/// Stores `$0` at address 3 of a 16 bytes heap with `store`, and returns what `load` reads back
+fn round_trip(store: &str, load: &str, value: i64) -> i64 {
+    let source = format!("STORE $1 #16\nALOC $1\nSTORE $1 #3\n{} $0 $1\n{} $1 $2", store, load);
+    run_with(&source, &[(0, value)]).registers()[2]
+}
+
+#[test]
+fn every_width_round_trips() {
+    assert_eq!(round_trip("STB", "LDB", 0x1234_5678_9ABC_DE7F), 0x7F);
+    assert_eq!(round_trip("STH", "LDH", 0x1234_5678_9ABC_7EF0), 0x7EF0);
+    assert_eq!(round_trip("STW", "LDW", 0x1234_5678_7ABC_DEF0), 0x7ABC_DEF0);
+    assert_eq!(round_trip("STW", "LDW", 0x1234_5678_9ABC_DEF0), 0x9ABC_DEF0_u32 as i32 as i64);
+    assert_eq!(round_trip("STD", "LDD", 0x1234_5678_9ABC_DEF0), 0x1234_5678_9ABC_DEF0);
+    assert_eq!(round_trip("STD", "LDD", i64::MIN + 5), i64::MIN + 5);
+}
+
+#[test]
+fn stores_are_big_endian() {
+    let vm = run_with("STORE $1 #8\nALOC $1\nSTORE $1 #0\nSTD $0 $1\nLDB $1 $2\nSTORE $1 #7\nLDB $1 $3", &[(0, 0x0102_0304_0506_0708)]);
+    assert_eq!(vm.registers()[2..4], [0x01, 0x08]);
+}
+
+#[test]
+fn loads_and_stores_past_the_heap_fault() {
+    for (access, address) in [("LDB $1 $2", 8), ("LDH $1 $2", 7), ("LDW $1 $2", 5), ("LDD $1 $2", 1), ("STD $2 $1", 1), ("STB $2 $1", -1)] {
+        let mut vm = assemble(&format!("STORE $0 #8\nALOC $0\nSTORE $1 #{}\n{}", address, access));
+        let opcode = OpCode::from(program(access)[0]);
+        let error = VmError { program_counter: 14, opcode, kind: VmErrorKind::HeapOutOfBounds(address) };
+        assert_eq!(vm.run(), Err(error), "{}", access);
+    }
+    let registers = run("STORE $0 #8\nALOC $0\nSTORE $1 #0\nNOT $3 $3\nSTD $3 $1\nLDD $1 $2");
+    assert_eq!(registers[2], -1);
+}
+
+#[test]
+fn the_heap_is_empty_at_first() {
+    let mut vm = assemble("LDB $0 $1");
+    let error = VmError { program_counter: 0, opcode: OpCode::LDB, kind: VmErrorKind::HeapOutOfBounds(0) };
+    assert_eq!(vm.run(), Err(error));
+}
+
+#[test]
+fn aloc_fails_on_a_negative_size() {
+    let mut vm = assemble("STORE $0 #-1\nALOC $0");
+    let error = VmError { program_counter: 6, opcode: OpCode::ALOC, kind: VmErrorKind::AllocationFailed(-1) };
+    assert_eq!(vm.run(), Err(error));
+}
+
+#[test]
+fn aloc_fails_past_the_maximum_heap_size() {
+    let mut vm = assemble("ALOC $0");
+    vm.set_register(0, MAX_HEAP_SIZE as i64 - 4);
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    vm.set_program_counter(0);
+    vm.set_register(0, 5);
+    assert_eq!(vm.run(), Err(VmError { program_counter: 0, opcode: OpCode::ALOC, kind: VmErrorKind::AllocationFailed(5) }));
+    vm.set_register(0, 4);
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    vm.set_register(0, i64::MAX);
+    vm.set_program_counter(0);
+    assert_eq!(vm.run(), Err(VmError { program_counter: 0, opcode: OpCode::ALOC, kind: VmErrorKind::AllocationFailed(i64::MAX) }));
+}
+
+#[test]
+fn with_heap_size_is_capped_to_the_maximum_heap_size() {
+    let mut vm = assemble("LDB $0 $1").with_heap_size(MAX_HEAP_SIZE + 100);
+    vm.set_register(0, MAX_HEAP_SIZE as i64 - 1);
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    vm.set_program_counter(0);
+    vm.set_register(0, MAX_HEAP_SIZE as i64);
+    let error = VmError { program_counter: 0, opcode: OpCode::LDB, kind: VmErrorKind::HeapOutOfBounds(MAX_HEAP_SIZE as i64) };
+    assert_eq!(vm.run(), Err(error));
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use std::io::{self, Write};
+
+use common::program;
+use reg_byte::OpCode;
+use reg_lang_vm::{ExitStatus, RegLangVM, VmError, VmErrorKind};
+
+/// Writer refusing every write
+struct BrokenPipe;
+impl Write for BrokenPipe {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn print_writes_to_the_captured_output() {
+    let mut vm = RegLangVM::new(program("STORE $0 #-42\nPRINT $0\nSTORE $1 #7\nPRINTLN $1\nFSTORE $f0 #1.5\nFPRINTLN $f0")).with_output(Vec::new());
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    let output = vm.output::<Vec<u8>>().expect("the output is a Vec<u8>");
+    assert_eq!(String::from_utf8_lossy(output), "-427\n1.5\n");
+}
+
+#[test]
+fn output_of_another_type_is_none() {
+    let vm = RegLangVM::new(vec![]).with_output(io::sink());
+    assert!(vm.output::<Vec<u8>>().is_none());
+    assert!(vm.output::<io::Sink>().is_some());
+}
+
+#[test]
+fn write_error_is_an_io_fault() {
+    let mut vm = RegLangVM::new(program("STORE $0 #1\nPRINTLN $0")).with_output(BrokenPipe);
+    let error = VmError { program_counter: 6, opcode: OpCode::PRINTLN, kind: VmErrorKind::Io(io::ErrorKind::BrokenPipe) };
+    assert_eq!(vm.run(), Err(error));
+}
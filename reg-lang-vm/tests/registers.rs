@@ -0,0 +1,39 @@
+mod common;
+
+use common::{run, run_with};
+
+#[test]
+fn mov_copies_a_register() {
+    let registers = run("STORE $0 #-42\nMOV $0 $1\nMOV $1 $31");
+    assert_eq!(registers[0], -42);
+    assert_eq!(registers[1], -42);
+    assert_eq!(registers[31], -42);
+}
+
+#[test]
+fn inc_and_dec_count_in_place() {
+    let registers = run("STORE $0 #5\nINC $0\nINC $0\nDEC $1\nDEC $1\nDEC $1");
+    assert_eq!(registers[0..2], [7, -3]);
+}
+
+#[test]
+fn inc_and_dec_wrap_with_the_flags() {
+    let vm = run_with("INC $0", &[(0, -1)]);
+    assert_eq!(vm.registers()[0], 0);
+    assert!(vm.carry_flag() && !vm.overflow_flag());
+    let vm = run_with("INC $0", &[(0, i64::MAX)]);
+    assert_eq!(vm.registers()[0], i64::MIN);
+    assert!(!vm.carry_flag() && vm.overflow_flag());
+    let vm = run_with("DEC $0", &[(0, 0)]);
+    assert_eq!(vm.registers()[0], -1);
+    assert!(vm.carry_flag() && !vm.overflow_flag());
+    let vm = run_with("DEC $0", &[(0, i64::MIN)]);
+    assert_eq!(vm.registers()[0], i64::MAX);
+    assert!(!vm.carry_flag() && vm.overflow_flag());
+}
+
+#[test]
+fn neg_negates_into_another_register() {
+    let registers = run("STORE $0 #12\nNEG $0 $1\nNEG $1 $2\nNEG $3 $3");
+    assert_eq!(registers[0..4], [12, -12, 12, 0]);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{assemble, run, run_with};
+use reg_byte::OpCode;
+use reg_lang_vm::{VmError, VmErrorKind};
+
+#[test]
+fn mod_keeps_the_sign_of_the_dividend() {
+    let registers = run("STORE $0 #7\nSTORE $1 #3\nMOD $0 $1 $2\nSTORE $0 #-7\nMOD $0 $1 $3\nSTORE $1 #-3\nMOD $0 $1 $4");
+    assert_eq!(registers[2..5], [1, -1, -1]);
+    let vm = run_with("MOD $0 $1 $2", &[(0, i64::MIN), (1, -1)]);
+    assert_eq!(vm.registers()[2], 0);
+}
+
+#[test]
+fn mod_by_zero_faults() {
+    let mut vm = assemble("STORE $0 #7\nMOD $0 $1 $2");
+    let error = VmError { program_counter: 6, opcode: OpCode::MOD, kind: VmErrorKind::DivisionByZero };
+    assert_eq!(vm.run(), Err(error));
+    assert_eq!(vm.registers()[2], 0);
+}
+
+#[test]
+fn getrem_copies_the_remainder_of_the_last_division() {
+    let registers = run("GETREM $0\nSTORE $1 #17\nSTORE $2 #5\nDIV $1 $2 $3\nGETREM $4\nSTORE $1 #-17\nDIV $1 $2 $3\nGETREM $5");
+    assert_eq!(registers[0], 0);
+    assert_eq!(registers[3..6], [-3, 2, -2]);
+}
+
+#[test]
+fn getflag_packs_the_equal_carry_and_overflow_flags() {
+    assert_eq!(run("GETFLAG $0")[0], 0);
+    assert_eq!(run("EQ $0 $1\nGETFLAG $0")[0], 0b001);
+    let vm = run_with("ADD $0 $1 $2\nGETFLAG $3", &[(0, -1), (1, 1)]);
+    assert_eq!(vm.registers()[3], 0b010);
+    let vm = run_with("ADD $0 $1 $2\nEQ $0 $0\nGETFLAG $3", &[(0, i64::MIN), (1, -1)]);
+    assert_eq!(vm.registers()[3], 0b111);
+}
+
+#[test]
+fn jmpne_only_jumps_when_the_comparison_was_false() {
+    let registers = run("\
+        STORE $0 #1
+        STORE $1 #2
+        EQ $0 $1
+        STORE $9 #skip
+        JMPNE $9
+        STORE $2 #1
+        skip:
+        EQ $0 $0
+        STORE $9 #end
+        JMPNE $9
+        STORE $3 #1
+        end:");
+    assert_eq!(registers[2..4], [0, 1]);
+}
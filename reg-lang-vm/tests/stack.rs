@@ -0,0 +1,68 @@
+mod common;
+
+use common::{assemble, program, run};
+use reg_byte::OpCode;
+use reg_lang_vm::{ExitStatus, VmError, VmErrorKind, CALL_STACK_SIZE, STACK_SIZE};
+
+#[test]
+fn pop_returns_the_pushed_values_last_first() {
+    let registers = run("STORE $0 #1\nSTORE $1 #-2\nPUSH $0\nPUSH $1\nPUSH $0\nPOP $2\nPOP $3\nPOP $4");
+    assert_eq!(registers[2..5], [1, -2, 1]);
+}
+
+#[test]
+fn call_and_ret_nest() {
+    let registers = run("\
+        STORE $9 #outer
+        CALL $9
+        STORE $3 #1
+        STORE $9 #end
+        JMP $9
+        outer:
+        INC $1
+        STORE $8 #inner
+        CALL $8
+        INC $1
+        RET
+        inner:
+        STORE $2 #7
+        RET
+        end:");
+    assert_eq!(registers[1..4], [2, 7, 1]);
+}
+
+#[test]
+fn push_on_a_full_stack_overflows() {
+    let mut vm = assemble("loop:\nPUSH $1\nINC $1\nSTORE $0 #loop\nJMP $0");
+    let error = VmError { program_counter: 0, opcode: OpCode::PUSH, kind: VmErrorKind::StackOverflow };
+    assert_eq!(vm.run(), Err(error));
+    assert_eq!(vm.registers()[1], STACK_SIZE as i64);
+    vm.load(program("POP $2"));
+    assert_eq!(vm.run(), Ok(ExitStatus::EndOfProgram));
+    assert_eq!(vm.registers()[2], STACK_SIZE as i64 - 1);
+}
+
+#[test]
+fn pop_on_an_empty_stack_underflows() {
+    let mut vm = assemble("STORE $0 #3\nPUSH $0\nPOP $1\nPOP $2");
+    let error = VmError { program_counter: 10, opcode: OpCode::POP, kind: VmErrorKind::StackUnderflow };
+    assert_eq!(vm.run(), Err(error));
+    assert_eq!(vm.registers()[1..3], [3, 0]);
+}
+
+#[test]
+fn endless_recursion_overflows_the_call_stack() {
+    let mut vm = assemble("f:\nSTORE $0 #f\nCALL $0");
+    let error = VmError { program_counter: 6, opcode: OpCode::CALL, kind: VmErrorKind::StackOverflow };
+    assert_eq!(vm.run(), Err(error));
+    assert_eq!(vm.call_stack().len(), CALL_STACK_SIZE);
+    assert_eq!(vm.program_counter(), 6);
+}
+
+#[test]
+fn ret_outside_of_a_call_underflows() {
+    let mut vm = assemble("INC $0\nRET");
+    let error = VmError { program_counter: 2, opcode: OpCode::RET, kind: VmErrorKind::StackUnderflow };
+    assert_eq!(vm.run(), Err(error));
+    assert_eq!(vm.program_counter(), 2);
+}
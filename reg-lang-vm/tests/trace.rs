@@ -0,0 +1,76 @@
+mod common;
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use common::assemble;
+use reg_lang_vm::{ExitStatus, JsonTracer, TextTracer, Tracer, VmError};
+
+/// Writer whose content can still be read once a tracer owns it
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `source` with the tracer writing to `Shared`, returns the result of the run and what the tracer wrote
+fn trace(source: &str, tracer: fn(Shared) -> Box<dyn Tracer>) -> (Result<ExitStatus, VmError>, String) {
+    let output = Shared::default();
+    let mut vm = assemble(source).with_tracer(tracer(output.clone()));
+    let result = vm.run();
+    let text = String::from_utf8(output.0.borrow().clone()).unwrap();
+    (result, text)
+}
+
+fn text(output: Shared) -> Box<dyn Tracer> {
+    Box::new(TextTracer::new(output))
+}
+
+fn json(output: Shared) -> Box<dyn Tracer> {
+    Box::new(JsonTracer::new(output))
+}
+
+/// Writes registers and flags, then faults on a division by zero. The faulting `DIV` has no effect and isn't traced
+const PROGRAM: &str = "STORE $0 #-1\nINC $0\nEQ $0 $1\nFSTORE $f2 #0.5\nPRINT $9\nDIV $0 $1 $2\nINC $0";
+
+#[test]
+fn text_trace() {
+    let (result, output) = trace(PROGRAM, text);
+    assert_eq!(result.unwrap_err().program_counter, 23);
+    assert_eq!(output, "\
+0000  STORE $0 #-1  ; $0 = -1
+0006  INC $0  ; $0 = 0, carry = true
+0008  EQ $0 $1  ; equal = true
+0011  FSTORE $f2 #0.5  ; $f2 = 0.5
+0021  PRINT $9
+");
+}
+
+#[test]
+fn json_trace() {
+    let (result, output) = trace(PROGRAM, json);
+    assert_eq!(result.unwrap_err().program_counter, 23);
+    assert_eq!(output, concat!(
+        r##"{"pc":0,"opcode":"STORE","operands":["$0","#-1"],"writes":[{"register":0,"value":-1}]}"##, "\n",
+        r##"{"pc":6,"opcode":"INC","operands":["$0"],"writes":[{"register":0,"value":0}],"carry_flag":true}"##, "\n",
+        r##"{"pc":8,"opcode":"EQ","operands":["$0","$1"],"writes":[],"equal_flag":true}"##, "\n",
+        r##"{"pc":11,"opcode":"FSTORE","operands":["$f2","#0.5"],"writes":[],"float_writes":[{"register":2,"value":0.5}]}"##, "\n",
+        r##"{"pc":21,"opcode":"PRINT","operands":["$9"],"writes":[]}"##, "\n",
+    ));
+}
+
+#[test]
+fn json_trace_of_flags_cleared_remainder_and_infinity() {
+    let source = "STORE $0 #-1\nINC $0\nSTORE $1 #7\nSTORE $2 #2\nDIV $1 $2 $3\nFSTORE $f0 #1.0\nFDIV $f0 $f1 $f1";
+    let (result, output) = trace(source, json);
+    assert_eq!(result, Ok(ExitStatus::EndOfProgram));
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[4], r##"{"pc":20,"opcode":"DIV","operands":["$1","$2","$3"],"writes":[{"register":3,"value":3}],"remainder":1,"carry_flag":false}"##);
+    assert_eq!(lines[6], r##"{"pc":34,"opcode":"FDIV","operands":["$f0","$f1","$f1"],"writes":[],"float_writes":[{"register":1,"value":null}]}"##);
+}
@@ -0,0 +1,233 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use std::collections::HashMap;
+
+use reg_byte::object::{self, ObjectFile};
+use reg_byte::rbg::RbgFile;
+use reg_lang_vm::{Debugger, JsonTracer, TextTracer, Tracer};
+
+use debug::DebugConsole;
+
+use super::*;
+
+pub const USAGE: &str = "\
+Usage:
+    reg-lang                                Start the REPL
+    reg-lang asm <file.rb> [-o <file.rbg>]  Assemble a source file into a .rbg file
+    reg-lang obj <file.rb> [-o <file.rbo>]  Assemble a source file into a .rbo object file
+    reg-lang link <file.rbo|file.rb>... [-o <file.rbg>]
+                                            Link object files into a .rbg file, the program starts in the first one
+    reg-lang run <file.rb|file.rbg> [--trace|--trace-json]
+                                            Run a source file or a .rbg file, tracing every instruction on stderr
+    reg-lang disasm <file.rbg|file.rb>      Print the instructions of a program
+    reg-lang check <file.rb>                Check that a source file assembles
+    reg-lang debug <file.rb|file.rbg> [--input <file>]
+                                            Step through a program in the debugger, READ and READC read the
+                                            input file or nothing as stdin holds the debugger commands";
+
+/// Exit code for a source that doesn't assemble, a VM fault or an unreadable file
+const FAILURE: u8 = 1;
+/// Exit code for invalid arguments
+const USAGE_ERROR: u8 = 2;
+
+/// Action requested on the command line
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Repl,
+    Asm { input: PathBuf, output: PathBuf },
+    Obj { input: PathBuf, output: PathBuf },
+    Link { inputs: Vec<PathBuf>, output: PathBuf },
+    Run { input: PathBuf, trace: Option<TraceFormat> },
+    Disasm { input: PathBuf },
+    Check { input: PathBuf },
+    Debug { input: PathBuf, program_input: Option<PathBuf> },
+}
+
+/// How `run` traces the executed instructions
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TraceFormat {
+    Text,
+    Json,
+}
+impl TraceFormat {
+    /// Creates a tracer writing to stderr
+    pub fn tracer(self) -> Box<dyn Tracer> {
+        match self {
+            TraceFormat::Text => Box::new(TextTracer::new(io::stderr())),
+            TraceFormat::Json => Box::new(JsonTracer::new(io::stderr())),
+        }
+    }
+}
+
+/// Parses the arguments following the program name
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let command = match args.next() {
+        None => return Ok(Command::Repl),
+        Some(command) => command,
+    };
+    let input = PathBuf::from(args.next().ok_or_else(|| format!("missing input file for {}", command))?);
+    let command = match command.as_str() {
+        "asm" | "obj" => {
+            let extension = if command == "asm" { "rbg" } else { "rbo" };
+            let output = match args.next().as_deref() {
+                None => input.with_extension(extension),
+                Some("-o") => PathBuf::from(args.next().ok_or("missing file after -o")?),
+                Some(arg) => return Err(format!("unexpected argument {}", arg)),
+            };
+            if command == "asm" {
+                Command::Asm { input, output }
+            } else {
+                Command::Obj { input, output }
+            }
+        },
+        "link" => {
+            let mut output = input.with_extension("rbg");
+            let mut inputs = vec![input];
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "-o" => output = PathBuf::from(args.next().ok_or("missing file after -o")?),
+                    _ => inputs.push(PathBuf::from(arg)),
+                }
+            }
+            Command::Link { inputs, output }
+        },
+        "run" => {
+            let trace = match args.next().as_deref() {
+                None => None,
+                Some("--trace") => Some(TraceFormat::Text),
+                Some("--trace-json") => Some(TraceFormat::Json),
+                Some(arg) => return Err(format!("unexpected argument {}", arg)),
+            };
+            Command::Run { input, trace }
+        },
+        "disasm" => Command::Disasm { input },
+        "check" => Command::Check { input },
+        "debug" => {
+            let program_input = match args.next().as_deref() {
+                None => None,
+                Some("--input") => Some(PathBuf::from(args.next().ok_or("missing file after --input")?)),
+                Some(arg) => return Err(format!("unexpected argument {}", arg)),
+            };
+            Command::Debug { input, program_input }
+        },
+        _ => return Err(format!("unknown command {}", command)),
+    };
+    match args.next() {
+        Some(arg) => Err(format!("unexpected argument {}", arg)),
+        None => Ok(command),
+    }
+}
+
+/// Runs the command, reporting errors on stderr
+pub fn execute(command: Command) -> ExitCode {
+    let result = match command {
+        Command::Repl => {
+            REPL::new().run_console();
+            Ok(())
+        },
+        Command::Asm { input, output } => assemble(&input).and_then(|file| {
+            fs::write(&output, file.to_bytes()).map_err(|e| format!("{}: {}", output.display(), e))
+        }),
+        Command::Obj { input, output } => compile_object(&input).and_then(|file| {
+            fs::write(&output, file.to_bytes()).map_err(|e| format!("{}: {}", output.display(), e))
+        }),
+        Command::Link { inputs, output } => link(&inputs).and_then(|file| {
+            fs::write(&output, file.to_bytes()).map_err(|e| format!("{}: {}", output.display(), e))
+        }),
+        Command::Run { input, trace } => load(&input).and_then(|file| {
+            let mut vm = RegLangVM::from_rbg(file);
+            vm.set_tracer(trace.map(TraceFormat::tracer));
+            vm.run().map(|_| ()).map_err(|e| format!("{}: {}", input.display(), e))
+        }),
+        Command::Disasm { input } => load(&input).and_then(|file| {
+            print!("{}", reg_byte::listing(&file.code));
+            match reg_byte::disassemble(&file.code).into_iter().find_map(|line| line.instruction.err()) {
+                Some(e) => Err(format!("{}: {}", input.display(), e)),
+                None => Ok(()),
+            }
+        }),
+        Command::Check { input } => assemble(&input).map(|_| ()),
+        Command::Debug { input, program_input } => load_with_labels(&input).and_then(|(file, labels)| {
+            // stdin holds the debugger commands, the program never reads from it
+            let program_input: Box<dyn io::Read> = match program_input {
+                Some(path) => Box::new(fs::File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?),
+                None => Box::new(io::empty()),
+            };
+            let vm = RegLangVM::from_rbg(file).with_input(program_input);
+            DebugConsole::new(Debugger::new(vm, labels)).run_console();
+            Ok(())
+        }),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("ERROR: {}", message);
+            ExitCode::from(FAILURE)
+        },
+    }
+}
+
+/// Reports invalid arguments with the usage
+pub fn usage_error(message: &str) -> ExitCode {
+    eprintln!("ERROR: {}\n\n{}", message, USAGE);
+    ExitCode::from(USAGE_ERROR)
+}
+
+/// Assembles a source file
+fn assemble(input: &Path) -> Result<RbgFile, String> {
+    Ok(RbgFile::new(compile(input)?.program))
+}
+
+/// Runs the assembler on a source file
+fn compile(input: &Path) -> Result<RegCompiler, String> {
+    let source = fs::read_to_string(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+    let directory = input.parent().unwrap_or(Path::new(""));
+    RegCompiler::compile_in(&source, directory).map_err(|e| format!("{}:{}", input.display(), e))
+}
+
+/// Assembles a source file into an object file
+fn compile_object(input: &Path) -> Result<ObjectFile, String> {
+    let source = fs::read_to_string(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+    let directory = input.parent().unwrap_or(Path::new(""));
+    RegCompiler::compile_object(&source, directory).map_err(|e| format!("{}:{}", input.display(), e))
+}
+
+/// Links object files, source files are assembled into object files first
+fn link(inputs: &[PathBuf]) -> Result<RbgFile, String> {
+    let mut modules = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let module = if input.extension().is_some_and(|extension| extension == "rbo") {
+            let bytes = fs::read(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+            ObjectFile::from_bytes(&bytes).map_err(|e| format!("{}: {}", input.display(), e))?
+        } else {
+            compile_object(input)?
+        };
+        modules.push(module);
+    }
+    object::link(&modules).map_err(|e| format!("{}: {}", inputs[e.module()].display(), e))
+}
+
+/// Reads a `.rbg` file, or assembles the file if it has another extension
+fn load(input: &Path) -> Result<RbgFile, String> {
+    if input.extension().is_some_and(|extension| extension == "rbg") {
+        let bytes = fs::read(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+        RbgFile::from_bytes(&bytes).map_err(|e| format!("{}: {}", input.display(), e))
+    } else {
+        assemble(input)
+    }
+}
+
+/// Same as `load`, with the labels of the program when it's a source file
+fn load_with_labels(input: &Path) -> Result<(RbgFile, HashMap<String, usize>), String> {
+    if input.extension().is_some_and(|extension| extension == "rbg") {
+        Ok((load(input)?, HashMap::new()))
+    } else {
+        let compiler = compile(input)?;
+        Ok((RbgFile::new(compiler.program), compiler.labels))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use super::{parse_args, Command, TraceFormat};
+
+fn parse(args: &[&str]) -> Result<Command, String> {
+    parse_args(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn no_arguments_start_the_repl() {
+    assert_eq!(parse(&[]), Ok(Command::Repl));
+}
+
+#[test]
+fn asm_and_obj_default_to_the_input_with_another_extension() {
+    assert_eq!(parse(&["asm", "dir/main.rb"]), Ok(Command::Asm { input: PathBuf::from("dir/main.rb"), output: PathBuf::from("dir/main.rbg") }));
+    assert_eq!(parse(&["obj", "dir/main.rb"]), Ok(Command::Obj { input: PathBuf::from("dir/main.rb"), output: PathBuf::from("dir/main.rbo") }));
+    assert_eq!(parse(&["asm", "main"]), Ok(Command::Asm { input: PathBuf::from("main"), output: PathBuf::from("main.rbg") }));
+}
+
+#[test]
+fn asm_and_obj_take_an_output() {
+    assert_eq!(parse(&["asm", "main.rb", "-o", "out.rbg"]), Ok(Command::Asm { input: PathBuf::from("main.rb"), output: PathBuf::from("out.rbg") }));
+    assert_eq!(parse(&["obj", "main.rb", "-o", "out.rbo"]), Ok(Command::Obj { input: PathBuf::from("main.rb"), output: PathBuf::from("out.rbo") }));
+}
+
+#[test]
+fn link_takes_every_input_and_defaults_to_the_first_one() {
+    let inputs = vec![PathBuf::from("main.rbo"), PathBuf::from("lib.rb")];
+    assert_eq!(parse(&["link", "main.rbo", "lib.rb"]), Ok(Command::Link { inputs: inputs.clone(), output: PathBuf::from("main.rbg") }));
+    assert_eq!(parse(&["link", "main.rbo", "-o", "out.rbg", "lib.rb"]), Ok(Command::Link { inputs, output: PathBuf::from("out.rbg") }));
+}
+
+#[test]
+fn run_takes_a_trace_format() {
+    assert_eq!(parse(&["run", "main.rb"]), Ok(Command::Run { input: PathBuf::from("main.rb"), trace: None }));
+    assert_eq!(parse(&["run", "main.rbg", "--trace"]), Ok(Command::Run { input: PathBuf::from("main.rbg"), trace: Some(TraceFormat::Text) }));
+    assert_eq!(parse(&["run", "main.rbg", "--trace-json"]), Ok(Command::Run { input: PathBuf::from("main.rbg"), trace: Some(TraceFormat::Json) }));
+}
+
+#[test]
+fn disasm_and_check_take_a_file() {
+    assert_eq!(parse(&["disasm", "main.rbg"]), Ok(Command::Disasm { input: PathBuf::from("main.rbg") }));
+    assert_eq!(parse(&["check", "main.rb"]), Ok(Command::Check { input: PathBuf::from("main.rb") }));
+}
+
+#[test]
+fn debug_takes_an_input_file() {
+    assert_eq!(parse(&["debug", "main.rb"]), Ok(Command::Debug { input: PathBuf::from("main.rb"), program_input: None }));
+    assert_eq!(
+        parse(&["debug", "main.rb", "--input", "numbers.txt"]),
+        Ok(Command::Debug { input: PathBuf::from("main.rb"), program_input: Some(PathBuf::from("numbers.txt")) }),
+    );
+}
+
+#[test]
+fn missing_arguments_are_errors() {
+    for command in ["asm", "obj", "link", "run", "disasm", "check", "debug"] {
+        assert_eq!(parse(&[command]), Err(format!("missing input file for {}", command)));
+    }
+    assert_eq!(parse(&["asm", "main.rb", "-o"]), Err("missing file after -o".to_string()));
+    assert_eq!(parse(&["obj", "main.rb", "-o"]), Err("missing file after -o".to_string()));
+    assert_eq!(parse(&["link", "main.rbo", "-o"]), Err("missing file after -o".to_string()));
+    assert_eq!(parse(&["debug", "main.rb", "--input"]), Err("missing file after --input".to_string()));
+}
+
+#[test]
+fn extra_arguments_are_errors() {
+    assert_eq!(parse(&["asm", "main.rb", "other.rb"]), Err("unexpected argument other.rb".to_string()));
+    assert_eq!(parse(&["asm", "main.rb", "-o", "out.rbg", "-v"]), Err("unexpected argument -v".to_string()));
+    assert_eq!(parse(&["run", "main.rb", "--trace", "--trace-json"]), Err("unexpected argument --trace-json".to_string()));
+    assert_eq!(parse(&["run", "main.rb", "--verbose"]), Err("unexpected argument --verbose".to_string()));
+    assert_eq!(parse(&["disasm", "main.rbg", "main.rb"]), Err("unexpected argument main.rb".to_string()));
+    assert_eq!(parse(&["check", "main.rb", "-o", "out"]), Err("unexpected argument -o".to_string()));
+    assert_eq!(parse(&["debug", "main.rb", "--input", "a.txt", "b.txt"]), Err("unexpected argument b.txt".to_string()));
+}
+
+#[test]
+fn unknown_command_is_an_error() {
+    assert_eq!(parse(&["build", "main.rb"]), Err("unknown command build".to_string()));
+}
@@ -0,0 +1,188 @@
+use reg_lang_vm::{Debugger, ExitStatus};
+
+use super::*;
+
+const HELP: &str = "\
+Commands:
+    break <label|offset>                  (b)  Add a breakpoint
+    delete <label|offset>                 (d)  Remove a breakpoint
+    breakpoints                                List the breakpoints
+    step [count]                          (s)  Execute the next instructions
+    continue                              (c)  Run until a breakpoint or the end of the program
+    registers                             (r)  Show the registers, the float registers, the remainder and the flags
+    set $N|$fN|pc|remainder|<flag> <value>     Change a register, a float register, the program counter, the remainder
+                                               or a flag (equal, carry or overflow)
+    list                                  (l)  Show the disassembled program
+    help                                  (h)  Show this help
+    quit                                  (q)  Exit the debugger";
+
+/// Interactive front end of the `Debugger`
+pub struct DebugConsole {
+    debugger: Debugger,
+}
+
+impl DebugConsole {
+    pub fn new(debugger: Debugger) -> Self {
+        Self { debugger }
+    }
+    pub fn run_console(&mut self) {
+        println!("Reg-Lang debugger, type help to see the commands");
+        self.show_current();
+        loop {
+            let mut buffer = String::new();
+
+            print!("(debug) ");
+            io::stdout().flush().expect("ERROR: Unable to flush stdout");
+
+            match io::stdin().read_line(&mut buffer) {
+                Ok(0) => return,
+                Ok(_) => {},
+                Err(e) => {
+                    println!("ERROR: Unable to read line from user: {}", e);
+                    return;
+                },
+            }
+            let mut words = buffer.split_whitespace();
+            let command = match words.next() {
+                Some(command) => command,
+                None => continue,
+            };
+            let args: Vec<&str> = words.collect();
+            match (command, args.as_slice()) {
+                ("break" | "b", [location]) => match self.debugger.add_breakpoint(location) {
+                    Ok(offset) => println!("Breakpoint at byte {}", offset),
+                    Err(e) => println!("ERROR: {}", e),
+                },
+                ("delete" | "d", [location]) => match self.debugger.remove_breakpoint(location) {
+                    Some(offset) => println!("Removed the breakpoint at byte {}", offset),
+                    None => println!("ERROR: no breakpoint at {}", location),
+                },
+                ("breakpoints", []) => {
+                    for offset in self.debugger.breakpoints() {
+                        println!("{}", self.describe(offset));
+                    }
+                },
+                ("step" | "s", []) => self.step(1),
+                ("step" | "s", [count]) => match count.parse() {
+                    Ok(count) => self.step(count),
+                    Err(_) => println!("ERROR: invalid count {}", count),
+                },
+                ("continue" | "c", []) => {
+                    let result = self.debugger.resume();
+                    self.report(result);
+                },
+                ("registers" | "r", []) => self.show_registers(),
+                ("set", [target, value]) => {
+                    if let Err(message) = self.set(target, value) {
+                        println!("ERROR: {}", message);
+                    }
+                },
+                ("list" | "l", []) => self.list(),
+                ("help" | "h", []) => println!("{}", HELP),
+                ("quit" | "q", []) => return,
+                _ => println!("ERROR: invalid command, type help to see the commands"),
+            }
+        }
+    }
+    /// Executes `count` instructions, stopping early if the program stops
+    fn step(&mut self, count: usize) {
+        for _ in 0..count {
+            let result = self.debugger.step();
+            if result != Ok(ExitStatus::Running) {
+                return self.report(result);
+            }
+        }
+        self.show_current();
+    }
+    /// Prints why the program stopped and where
+    fn report(&mut self, result: Result<ExitStatus, reg_lang_vm::VmError>) {
+        match result {
+            Ok(ExitStatus::Running | ExitStatus::Breakpoint(_)) => {},
+            Ok(status) => println!("Program stopped: {:?}", status),
+            Err(e) => println!("ERROR: {}", e),
+        }
+        self.show_current();
+    }
+    fn set(&mut self, target: &str, value: &str) -> Result<(), String> {
+        if target == "pc" {
+            let offset = self.debugger.resolve(value).ok_or(format!("unknown label or offset {}", value))?;
+            self.debugger.vm.set_program_counter(offset);
+            return Ok(());
+        }
+        let vm = &mut self.debugger.vm;
+        match target {
+            "remainder" => vm.set_remainder(value.parse().map_err(|_| format!("invalid remainder {}", value))?),
+            "equal" => vm.set_equal_flag(value.parse().map_err(|_| format!("invalid flag {}, expected true or false", value))?),
+            "carry" => vm.set_carry_flag(value.parse().map_err(|_| format!("invalid flag {}, expected true or false", value))?),
+            "overflow" => vm.set_overflow_flag(value.parse().map_err(|_| format!("invalid flag {}, expected true or false", value))?),
+            _ if target.starts_with("$f") => {
+                let register = target[2..].parse::<usize>().ok()
+                    .filter(|index| *index < reg_byte::REGISTER_COUNT)
+                    .ok_or(format!("unknown float register {}", target))?;
+                vm.set_float_register(register, value.parse().map_err(|_| format!("invalid float {}", value))?);
+            },
+            _ => {
+                let register = target.strip_prefix('$').and_then(|index| index.parse::<usize>().ok())
+                    .filter(|index| *index < reg_byte::REGISTER_COUNT)
+                    .ok_or(format!("unknown register {}", target))?;
+                vm.set_register(register, value.parse().map_err(|_| format!("invalid value {}", value))?);
+            },
+        }
+        Ok(())
+    }
+    fn show_registers(&self) {
+        let vm = &self.debugger.vm;
+        for (index, value) in vm.registers().iter().enumerate() {
+            print!("${:<2} = {:<20}", index, value);
+            if index % 4 == 3 {
+                println!();
+            }
+        }
+        for (index, value) in vm.float_registers().iter().enumerate() {
+            print!("$f{:<2} = {:<19}", index, value);
+            if index % 4 == 3 {
+                println!();
+            }
+        }
+        println!(
+            "pc = {}  remainder = {}  equal = {}  carry = {}  overflow = {}",
+            vm.program_counter(),
+            vm.remainder(),
+            vm.equal_flag(),
+            vm.carry_flag(),
+            vm.overflow_flag(),
+        );
+    }
+    /// Prints the disassembled program, with the current instruction and breakpoints marked
+    fn list(&self) {
+        let program_counter = self.debugger.vm.program_counter();
+        let breakpoints: Vec<usize> = self.debugger.breakpoints().collect();
+        for line in reg_byte::disassemble(&self.debugger.vm.program) {
+            if let Some(label) = self.debugger.label_at(line.offset) {
+                println!("        {}:", label);
+            }
+            let marker = if line.offset == program_counter { "=>" } else { "  " };
+            let breakpoint = if breakpoints.contains(&line.offset) { "*" } else { " " };
+            match line.instruction {
+                Ok(instruction) => println!("{}{} {:04}  {}", marker, breakpoint, line.offset, instruction),
+                Err(e) => println!("{}{} {:04}  ; {}", marker, breakpoint, line.offset, e),
+            }
+        }
+    }
+    /// Prints the next instruction to execute
+    fn show_current(&self) {
+        let offset = self.debugger.vm.program_counter();
+        match self.debugger.vm.current_instruction() {
+            Some(Ok(instruction)) => println!("=> {}  {}", self.describe(offset), instruction),
+            Some(Err(e)) => println!("=> {}  ; {}", self.describe(offset), e),
+            None => println!("=> {}  end of program", self.describe(offset)),
+        }
+    }
+    /// Byte offset followed by the label defined there, if any
+    fn describe(&self, offset: usize) -> String {
+        match self.debugger.label_at(offset) {
+            Some(label) => format!("{:04} ({})", offset, label),
+            None => format!("{:04}", offset),
+        }
+    }
+}
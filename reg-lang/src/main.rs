@@ -1,12 +1,17 @@
+pub mod cli;
+pub mod debug;
 pub mod repl;
 
 use repl::REPL;
 use std::io::Write;
 use std::io;
+use std::process::ExitCode;
 
 use reg_lang_compiler::RegCompiler;
 use reg_lang_vm::RegLangVM;
-fn main() {
-    let mut repl = REPL::new();
-    repl.run_console();
-}
\ No newline at end of file
+fn main() -> ExitCode {
+    match cli::parse_args(std::env::args().skip(1)) {
+        Ok(command) => cli::execute(command),
+        Err(message) => cli::usage_error(&message),
+    }
+}
@@ -1,10 +1,16 @@
 use super::*;
+use crate::cli::TraceFormat;
 
 pub struct REPL {
     command_buffer: Vec<String>,
     vm: RegLangVM,
 }
 
+impl Default for REPL {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl REPL {
     pub fn new() -> Self {
         Self {
@@ -16,6 +22,7 @@ impl REPL {
         println!("Reg-Lang REPL (v0.0.1) RedGear-Studio");
         println!("Type .quit or .exit to exit the REPL");
         println!("Type .history to see the command history");
+        println!("Type .trace on, .trace json or .trace off to trace the executed instructions");
         loop {
             let mut buffer = String::new();
 
@@ -24,7 +31,10 @@ impl REPL {
             print!("Reg-Lang > ");
             io::stdout().flush().expect("ERROR: Unable to flush stdout");
 
-            stdin.read_line(&mut buffer).expect("ERROR: Unable to read line from user");
+            if stdin.read_line(&mut buffer).expect("ERROR: Unable to read line from user") == 0 {
+                println!();
+                return;
+            }
             let buffer = buffer.trim();
 
             self.command_buffer.push(buffer.to_string());
@@ -35,14 +45,24 @@ impl REPL {
                         println!("{}", command);
                     }
                 },
+                ".trace on" => self.vm.set_tracer(Some(TraceFormat::Text.tracer())),
+                ".trace json" => self.vm.set_tracer(Some(TraceFormat::Json.tracer())),
+                ".trace off" => self.vm.set_tracer(None),
                 ".quit" | ".exit" => {
                     println!("Exiting...");
                     std::process::exit(0);
                 },
                 _ => {
-                    let bytecode = RegCompiler::compile(buffer).program;
-                    self.vm.program = bytecode;
-                    self.vm.run();
+                    match RegCompiler::compile(buffer) {
+                        Ok(compiler) => {
+                            // Every line is a new program, the registers and the heap stay as the last line left them
+                            self.vm.load(compiler.program);
+                            if let Err(e) = self.vm.run() {
+                                println!("ERROR: {}", e);
+                            }
+                        },
+                        Err(e) => println!("ERROR: {}", e),
+                    }
                 }
             }
         }